@@ -0,0 +1,176 @@
+use crate::commands::submit::resource_autocomplete;
+use crate::store::allocation::{ALLOCATION_LOCK, available_inventory, rebalance_store};
+use crate::store::{InventoryAdjustment, LedgerEntry, LedgerStore, RequestStatus};
+use crate::utils::catalog::{NameMatch, unknown_resource_message};
+use crate::utils::checks::is_officer;
+use crate::utils::resources::normalize_resource_key;
+use crate::{BotError, Context};
+use poise::serenity_prelude::User;
+
+pub enum AdjustOutcome {
+    /// Applied; how much is in stock now, the ledger transaction and the products
+    /// whose requests lost reserved stock to it
+    Applied {
+        left: u64,
+        txn_id: String,
+        released: Vec<String>,
+    },
+    /// Nothing changed, the stock would have gone below zero
+    Insufficient { stock: u64 },
+    /// Nothing changed, it would have taken stock open requests have reserved
+    Reserved {
+        available: u64,
+        products: Vec<String>,
+    },
+}
+
+/// Change the stock of `resource` by `delta` (never below zero), record the change and
+/// its reason in the ledger, and rebalance what open requests have reserved of it.
+/// With `keep_reserved`, stock reserved for open requests can't be taken out.
+pub async fn record_adjustment(
+    store: &dyn LedgerStore,
+    resource: &str,
+    delta: i64,
+    user: &User,
+    reason: Option<String>,
+    keep_reserved: bool,
+) -> Result<AdjustOutcome, BotError> {
+    let resource_key = normalize_resource_key(resource);
+    let _guard = ALLOCATION_LOCK.lock().await;
+
+    if keep_reserved && delta < 0 {
+        let rows = store.request_rows().await?;
+        let available = available_inventory(&store.inventory().await?, &rows)
+            .get(&resource_key)
+            .copied()
+            .unwrap_or(0);
+        if delta.unsigned_abs() > available {
+            let mut products: Vec<String> = Vec::new();
+            for row in &rows {
+                if row.status == RequestStatus::InProgress
+                    && row.reserved > 0
+                    && normalize_resource_key(&row.resource) == resource_key
+                    && !products.contains(&row.product)
+                {
+                    products.push(row.product.clone());
+                }
+            }
+            if !products.is_empty() {
+                return Ok(AdjustOutcome::Reserved {
+                    available,
+                    products,
+                });
+            }
+        }
+    }
+
+    let left = match store
+        .adjust_inventory(&[(resource_key.clone(), delta)])
+        .await?
+    {
+        InventoryAdjustment::Applied(inventory) => inventory.get(&resource_key).copied(),
+        InventoryAdjustment::Insufficient(short) => {
+            let stock = short.first().map(|(_, stock)| *stock).unwrap_or(0);
+            return Ok(AdjustOutcome::Insufficient { stock });
+        }
+    };
+
+    let entry = LedgerEntry::new(resource, delta, user, reason);
+    store.append_ledger(std::slice::from_ref(&entry)).await?;
+    let mut released: Vec<String> = Vec::new();
+    for change in rebalance_store(store, std::slice::from_ref(&resource_key), None).await? {
+        if change.delta < 0 && !released.contains(&change.product) {
+            released.push(change.product);
+        }
+    }
+
+    Ok(AdjustOutcome::Applied {
+        left: left.unwrap_or(0),
+        txn_id: entry.txn_id,
+        released,
+    })
+}
+
+/// "⚠️ Reserved stock taken from: …" for replies, or nothing
+pub fn released_note(released: &[String]) -> String {
+    if released.is_empty() {
+        String::new()
+    } else {
+        format!("\n⚠️ Reserved stock taken from: {}", released.join(", "))
+    }
+}
+
+/// Officer-only correction of the inventory, in either direction
+#[poise::command(slash_command, check = "is_officer")]
+pub async fn adjust(
+    ctx: Context<'_>,
+    #[description = "Resource to correct"]
+    #[autocomplete = "resource_autocomplete"]
+    resource: String,
+    #[description = "Amount to add (negative to remove)"] amount: i32,
+    #[description = "Why the correction is needed"] reason: String,
+) -> Result<(), BotError> {
+    ctx.defer().await?;
+    let resource = match ctx.data().catalog.find(&resource) {
+        NameMatch::Exact(name) | NameMatch::Corrected(name) => name,
+        NameMatch::Unknown(suggestions) => {
+            ctx.say(unknown_resource_message(&resource, &suggestions))
+                .await?;
+            return Ok(());
+        }
+    };
+    if amount == 0 {
+        ctx.say("❌ An adjustment of 0 wouldn't change anything.")
+            .await?;
+        return Ok(());
+    }
+
+    let outcome = record_adjustment(
+        ctx.data().store.as_ref(),
+        &resource,
+        amount.into(),
+        ctx.author(),
+        Some(format!("Adjustment: {}", reason)),
+        false,
+    )
+    .await?;
+
+    match outcome {
+        AdjustOutcome::Applied {
+            left,
+            txn_id,
+            released,
+        } => {
+            ctx.say(format!(
+                "✅ Adjusted {} by {:+} ({} in stock). Reason recorded in the ledger as transaction `{}`.{}",
+                resource,
+                amount,
+                left,
+                txn_id,
+                released_note(&released)
+            ))
+            .await?;
+        }
+        AdjustOutcome::Insufficient { stock } => {
+            ctx.say(format!(
+                "❌ Can't adjust {} by {:+}: only {} in the inventory.",
+                resource, amount, stock
+            ))
+            .await?;
+        }
+        AdjustOutcome::Reserved {
+            available,
+            products,
+        } => {
+            ctx.say(format!(
+                "❌ Can't adjust {} by {:+}: only {} isn't reserved for {}.",
+                resource,
+                amount,
+                available,
+                products.join(", ")
+            ))
+            .await?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,279 @@
+use crate::commands::submit::resource_autocomplete;
+use crate::store::allocation::{ALLOCATION_LOCK, rebalance_store};
+use crate::store::{
+    Inventory, InventoryAdjustment, LedgerEntry, LedgerStore, RequestRow, RequestStatus,
+};
+use crate::utils::catalog::Catalog;
+use crate::utils::checks::is_officer;
+use crate::utils::resources::normalize_resource_key;
+use crate::{BotError, Context};
+use poise::serenity_prelude::{AutocompleteChoice, User};
+
+/// Stock that was recorded under a name which is now an alias
+pub struct MovedStock {
+    pub alias: String,
+    pub name: String,
+    pub amount: u64,
+}
+
+/// Stock kept under a name the catalog now treats as an alias, which
+/// `fold_alias_stock` would move. Aliases still used by open request lines are left out.
+pub fn stock_under_aliases(
+    inventory: &Inventory,
+    rows: &[RequestRow],
+    catalog: &Catalog,
+) -> Vec<MovedStock> {
+    catalog
+        .aliases()
+        .into_iter()
+        .filter_map(|(alias, name)| {
+            let alias_key = normalize_resource_key(&alias);
+            let amount = inventory.get(&alias_key).copied().unwrap_or(0);
+            let in_use = rows.iter().any(|row| {
+                row.status == RequestStatus::InProgress
+                    && normalize_resource_key(&row.resource) == alias_key
+            });
+            (amount > 0 && !in_use).then_some(MovedStock {
+                alias,
+                name,
+                amount,
+            })
+        })
+        .collect()
+}
+
+/// Move stock kept under an alias over to the resource it stands for, with a ledger
+/// entry for each side. Only officers' catalog commands do this, so every move is
+/// recorded under whoever changed the catalog.
+pub async fn fold_alias_stock(
+    store: &dyn LedgerStore,
+    catalog: &Catalog,
+    user: &User,
+) -> Result<Vec<MovedStock>, BotError> {
+    let _guard = ALLOCATION_LOCK.lock().await;
+    let inventory = store.inventory().await?;
+    let rows = store.request_rows().await?;
+
+    let mut moved = Vec::new();
+    for MovedStock {
+        alias,
+        name,
+        amount,
+    } in stock_under_aliases(&inventory, &rows, catalog)
+    {
+        let alias_key = normalize_resource_key(&alias);
+        let name_key = normalize_resource_key(&name);
+        let deltas = [
+            (alias_key.clone(), -(amount as i64)),
+            (name_key.clone(), amount as i64),
+        ];
+        let InventoryAdjustment::Applied(_) = store.adjust_inventory(&deltas).await? else {
+            continue;
+        };
+        let reason = format!("Catalog: {} is now {}", alias, name);
+        let entries = [
+            LedgerEntry::new(&alias, -(amount as i64), user, Some(reason.clone())),
+            LedgerEntry::new(&name, amount as i64, user, Some(reason)),
+        ];
+        store.append_ledger(&entries).await?;
+        rebalance_store(store, &[alias_key, name_key], None).await?;
+        moved.push(MovedStock {
+            alias,
+            name,
+            amount,
+        });
+    }
+    Ok(moved)
+}
+
+// Products of the open requests with a line for `resource`
+async fn open_lines(store: &dyn LedgerStore, resource: &str) -> Result<Vec<String>, BotError> {
+    let key = normalize_resource_key(resource);
+    let mut products: Vec<String> = Vec::new();
+    for row in store.request_rows().await? {
+        if row.status == RequestStatus::InProgress
+            && normalize_resource_key(&row.resource) == key
+            && !products.contains(&row.product)
+        {
+            products.push(row.product);
+        }
+    }
+    Ok(products)
+}
+
+fn describe_moves(moved: &[MovedStock]) -> String {
+    moved
+        .iter()
+        .map(|m| {
+            format!(
+                "\n📦 Moved {} x {} in the inventory to {}.",
+                m.amount, m.alias, m.name
+            )
+        })
+        .collect()
+}
+
+// Categories already in use, so new resources don't invent near-duplicates
+async fn category_autocomplete<'a>(ctx: Context<'a>, partial: &str) -> Vec<AutocompleteChoice> {
+    let mut categories: Vec<String> = ctx
+        .data()
+        .catalog
+        .search("")
+        .into_iter()
+        .filter_map(|resource| resource.category)
+        .filter(|category| category.to_lowercase().contains(&partial.to_lowercase()))
+        .collect();
+    categories.sort();
+    categories.dedup();
+    categories
+        .into_iter()
+        .take(25)
+        .map(|category| AutocompleteChoice::new(category.clone(), category))
+        .collect()
+}
+
+#[poise::command(
+    slash_command,
+    subcommands("add", "rename", "remove", "alias", "reload"),
+    subcommand_required
+)]
+pub async fn catalog(_: Context<'_>) -> Result<(), BotError> {
+    Ok(())
+}
+
+/// Add a new resource to the catalog
+#[poise::command(slash_command, check = "is_officer")]
+pub async fn add(
+    ctx: Context<'_>,
+    #[description = "Resource name, as it should be shown"] name: String,
+    #[description = "Category it's listed under"]
+    #[autocomplete = "category_autocomplete"]
+    category: Option<String>,
+) -> Result<(), BotError> {
+    ctx.defer().await?;
+    ctx.data().catalog.add(&name, category)?;
+    ctx.say(format!("✅ Added **{}** to the catalog.", name.trim()))
+        .await?;
+    Ok(())
+}
+
+/// Rename a resource; the old name keeps working as an alias
+#[poise::command(slash_command, check = "is_officer")]
+pub async fn rename(
+    ctx: Context<'_>,
+    #[description = "Resource to rename"]
+    #[autocomplete = "resource_autocomplete"]
+    resource: String,
+    #[description = "New name"] new_name: String,
+) -> Result<(), BotError> {
+    ctx.defer().await?;
+    let store = ctx.data().store.as_ref();
+    let catalog = &ctx.data().catalog;
+    let old_name = {
+        // No request may open a line for it between the check and the rename
+        let _guard = ALLOCATION_LOCK.lock().await;
+        // Their lines are stored by name, so they'd stop matching the inventory
+        let open = open_lines(store, &resource).await?;
+        if !open.is_empty() {
+            ctx.say(format!(
+                "❌ {} still need {}. Finish or close them before renaming it.",
+                open.join(", "),
+                resource
+            ))
+            .await?;
+            return Ok(());
+        }
+        catalog.rename(&resource, &new_name)?
+    };
+    let moved = fold_alias_stock(store, catalog, ctx.author()).await?;
+    ctx.say(format!(
+        "✅ Renamed **{}** to **{}**; the old name still works as an alias.{}",
+        old_name,
+        new_name.trim(),
+        describe_moves(&moved)
+    ))
+    .await?;
+    Ok(())
+}
+
+/// Remove a resource (and its aliases) from the catalog
+#[poise::command(slash_command, check = "is_officer")]
+pub async fn remove(
+    ctx: Context<'_>,
+    #[description = "Resource to remove"]
+    #[autocomplete = "resource_autocomplete"]
+    resource: String,
+) -> Result<(), BotError> {
+    ctx.defer().await?;
+    let store = ctx.data().store.as_ref();
+    let removed = {
+        let _guard = ALLOCATION_LOCK.lock().await;
+        // Nothing could ever be submitted for them again
+        let open = open_lines(store, &resource).await?;
+        if !open.is_empty() {
+            ctx.say(format!(
+                "❌ {} still need {}. Finish or close them before removing it.",
+                open.join(", "),
+                resource
+            ))
+            .await?;
+            return Ok(());
+        }
+        ctx.data().catalog.remove(&resource)?
+    };
+    let stock = store
+        .inventory()
+        .await?
+        .get(&normalize_resource_key(&removed.name))
+        .copied()
+        .unwrap_or(0);
+
+    let mut reply = format!("✅ Removed **{}** from the catalog.", removed.name);
+    if stock > 0 {
+        reply.push_str(&format!(
+            "\n⚠️ {} are still in the inventory and can no longer be submitted or withdrawn.",
+            stock
+        ));
+    }
+    ctx.say(reply).await?;
+    Ok(())
+}
+
+/// Let another spelling stand for a resource
+#[poise::command(slash_command, check = "is_officer")]
+pub async fn alias(
+    ctx: Context<'_>,
+    #[description = "Resource the alias stands for"]
+    #[autocomplete = "resource_autocomplete"]
+    resource: String,
+    #[description = "Other name for it"] alias: String,
+) -> Result<(), BotError> {
+    ctx.defer().await?;
+    let catalog = &ctx.data().catalog;
+    let name = catalog.alias(&resource, &alias)?;
+    let moved = fold_alias_stock(ctx.data().store.as_ref(), catalog, ctx.author()).await?;
+    ctx.say(format!(
+        "✅ '{}' now means **{}**.{}",
+        alias.trim(),
+        name,
+        describe_moves(&moved)
+    ))
+    .await?;
+    Ok(())
+}
+
+/// Pick up hand edits to the catalog file
+#[poise::command(slash_command, check = "is_officer")]
+pub async fn reload(ctx: Context<'_>) -> Result<(), BotError> {
+    ctx.defer().await?;
+    let catalog = &ctx.data().catalog;
+    let count = catalog.reload()?;
+    let moved = fold_alias_stock(ctx.data().store.as_ref(), catalog, ctx.author()).await?;
+    ctx.say(format!(
+        "✅ Reloaded the catalog: {} resources.{}",
+        count,
+        describe_moves(&moved)
+    ))
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,170 @@
+use crate::store::allocation::{ALLOCATION_LOCK, rebalance_store};
+use crate::store::{InventoryAdjustment, LedgerEntry, LedgerStore, RequestRow, RequestStatus};
+use crate::utils::checks::is_officer;
+use crate::utils::resources::normalize_resource_key;
+use crate::{BotError, Context};
+use poise::serenity_prelude::User;
+
+pub enum RevertOutcome {
+    Reverted {
+        compensating_txn: String,
+        resource: String,
+        amount: i64,
+        /// Products whose requests had reserved stock released
+        released: Vec<String>,
+    },
+    NotFound,
+    AlreadyReverted {
+        by_txn: String,
+    },
+    /// Reverts are undone by `/adjust`, not by reverting the revert
+    IsRevert,
+    /// The stock it added has already been used or withdrawn
+    Insufficient {
+        stock: u64,
+    },
+}
+
+/// Undo a ledger transaction without deleting history: reverse its inventory effect,
+/// release reservations that relied on it, and append a compensating entry
+pub async fn revert_transaction(
+    store: &dyn LedgerStore,
+    txn_id: &str,
+    user: &User,
+) -> Result<RevertOutcome, BotError> {
+    // Rows written before IDs existed have an empty one, and can't be picked out
+    let txn_id = txn_id.trim();
+    if txn_id.is_empty() {
+        return Ok(RevertOutcome::NotFound);
+    }
+    // Also keeps two officers reverting the same transaction from both succeeding
+    let _guard = ALLOCATION_LOCK.lock().await;
+
+    let entries = store.ledger_entries().await?;
+    let Some(original) = entries
+        .iter()
+        .find(|e| !e.txn_id.is_empty() && e.txn_id == txn_id)
+    else {
+        return Ok(RevertOutcome::NotFound);
+    };
+    if original.reverts.is_some() {
+        return Ok(RevertOutcome::IsRevert);
+    }
+    if let Some(revert) = entries
+        .iter()
+        .find(|e| e.reverts.as_deref() == Some(txn_id))
+    {
+        return Ok(RevertOutcome::AlreadyReverted {
+            by_txn: revert.txn_id.clone(),
+        });
+    }
+
+    let resource_key = normalize_resource_key(&original.resource);
+    if let InventoryAdjustment::Insufficient(short) = store
+        .adjust_inventory(&[(resource_key.clone(), -original.amount)])
+        .await?
+    {
+        let stock = short.first().map(|(_, stock)| *stock).unwrap_or(0);
+        return Ok(RevertOutcome::Insufficient { stock });
+    }
+
+    // The requests this transaction fed give back what it handed them first
+    let mut released = Vec::new();
+    let mut fed: Vec<RequestRow> = Vec::new();
+    let mut rows = store.request_rows().await?;
+    for (request_id, amount) in &original.allocations {
+        let Some(row) = rows.iter_mut().find(|row| {
+            row.request_id == *request_id
+                && row.status == RequestStatus::InProgress
+                && normalize_resource_key(&row.resource) == resource_key
+        }) else {
+            continue;
+        };
+        let given_back = (*amount).min(row.reserved);
+        if given_back == 0 {
+            continue;
+        }
+        row.reserved -= given_back;
+        if !released.contains(&row.product) {
+            released.push(row.product.clone());
+        }
+        fed.push(row.clone());
+    }
+    if !fed.is_empty() {
+        store.update_request_rows(&fed).await?;
+    }
+    // Whatever is still short comes back from the rest of the queue as usual
+    for change in rebalance_store(store, std::slice::from_ref(&resource_key), None).await? {
+        if change.delta < 0 && !released.contains(&change.product) {
+            released.push(change.product);
+        }
+    }
+
+    let mut compensation = LedgerEntry::new(
+        &original.resource,
+        -original.amount,
+        user,
+        Some(format!("Revert of {}", txn_id)),
+    );
+    compensation.reverts = Some(txn_id.to_string());
+    store
+        .append_ledger(std::slice::from_ref(&compensation))
+        .await?;
+
+    Ok(RevertOutcome::Reverted {
+        compensating_txn: compensation.txn_id,
+        resource: original.resource.clone(),
+        amount: original.amount,
+        released,
+    })
+}
+
+#[poise::command(slash_command, subcommands("revert"), subcommand_required)]
+pub async fn ledger(_: Context<'_>) -> Result<(), BotError> {
+    Ok(())
+}
+
+/// Reverse a transaction (e.g. a mistyped submission) with a compensating ledger entry
+#[poise::command(slash_command, check = "is_officer")]
+pub async fn revert(
+    ctx: Context<'_>,
+    #[description = "Transaction ID shown when it was recorded"] txn_id: String,
+) -> Result<(), BotError> {
+    ctx.defer().await?;
+    let txn_id = txn_id.trim();
+    let reply = match revert_transaction(ctx.data().store.as_ref(), txn_id, ctx.author()).await? {
+        RevertOutcome::Reverted {
+            compensating_txn,
+            resource,
+            amount,
+            released,
+        } => {
+            let mut reply = format!(
+                "✅ Reverted `{}` ({:+} x {}) with transaction `{}`.",
+                txn_id, amount, resource, compensating_txn
+            );
+            if !released.is_empty() {
+                reply.push_str(&format!(
+                    "\nReserved stock released from: {}",
+                    released.join(", ")
+                ));
+            }
+            reply
+        }
+        RevertOutcome::NotFound => format!("❌ No ledger transaction `{}`.", txn_id),
+        RevertOutcome::AlreadyReverted { by_txn } => {
+            format!("❌ `{}` was already reverted by `{}`.", txn_id, by_txn)
+        }
+        RevertOutcome::IsRevert => format!(
+            "❌ `{}` is itself a revert. Use `/adjust` to correct it instead.",
+            txn_id
+        ),
+        RevertOutcome::Insufficient { stock } => format!(
+            "❌ Can't revert `{}`: only {} of that resource is left in the inventory.",
+            txn_id, stock
+        ),
+    };
+
+    ctx.say(reply).await?;
+    Ok(())
+}
@@ -1,2 +1,6 @@
+pub mod adjust;
+pub mod catalog;
+pub mod ledger;
 pub mod request;
 pub mod submit;
+pub mod withdraw;
@@ -1,32 +1,29 @@
-use crate::utils::sheets::{load_inventory_from_sheets, normalize_resource_key};
+use crate::commands::submit::resource_autocomplete;
+use crate::store::allocation::{ALLOCATION_LOCK, available_inventory, rebalance_store};
+use crate::store::{
+    InventoryAdjustment, LedgerEntry, LedgerStore, RequestPriority, RequestRow, RequestStatus,
+};
+use crate::utils::build_plan::{BuildPlan, parse_build_plan};
+use crate::utils::catalog::{Catalog, NameMatch, suggestion_hint, unknown_resource_message};
+use crate::utils::checks::{author_is_officer, is_officer};
+use crate::utils::drafts::{self, InProgressRequest};
+use crate::utils::material_list::parse_material_list;
+use crate::utils::resources::{
+    Conversions, ListedResource, listed_resources, normalize_resource_key,
+};
 use crate::{BotError, Context};
-use dashmap::DashMap;
-use dotenvy::dotenv;
-use google_sheets4 as sheets4;
-use hyper_rustls::HttpsConnectorBuilder;
-use hyper_util::client::legacy::Client as LegacyClient;
-use hyper_util::rt::TokioExecutor;
-use once_cell::sync::Lazy;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
 use poise::CreateReply;
-use poise::serenity_prelude::{
-    ButtonStyle, ChannelId, CreateButton, CreateEmbed, CreateMessage, CreateThread, Message,
-    MessageId, UserId,
+use poise::serenity_prelude as serenity;
+use serenity::AutocompleteChoice;
+use serenity::{
+    ButtonStyle, ChannelId, ComponentInteraction, CreateButton, CreateEmbed, CreateMessage,
+    CreateThread, EditThread, Message,
 };
-use regex::Regex;
-use sheets4::{Sheets, api::ValueRange, hyper_rustls, yup_oauth2};
+use std::cmp::Reverse;
 use std::collections::HashMap;
-use std::env::var;
 use uuid::Uuid;
-use yup_oauth2::{ServiceAccountAuthenticator, read_service_account_key};
-const SERVICE_ACCOUNT_PATH: &str = "secrets/voltaic-bridge-465115-j2-f15defee98d4.json";
-// For storing an ongoing request in the bot's memory
-struct InProgressRequest {
-    product: String,
-    resources: Vec<(u64, String)>,
-    _sheet_row_ids: Vec<String>,
-    _message_id: MessageId,
-}
-static IN_FLIGHT: Lazy<DashMap<UserId, InProgressRequest>> = Lazy::new(Default::default);
+type ResourceLines = Vec<(u64, String)>;
 
 // Build the embed to post with a resource update
 pub fn build_update_embed(
@@ -76,7 +73,7 @@ pub fn build_update_embed(
 pub fn compute_completed_remaining(
     resources: &[(u64, String)],
     inventory: &HashMap<String, u64>,
-) -> (Vec<(u64, String)>, Vec<(u64, String)>) {
+) -> (ResourceLines, ResourceLines) {
     let mut completed = Vec::new();
     let mut remaining = Vec::new();
     for &(req_amt, ref name) in resources {
@@ -91,152 +88,503 @@ pub fn compute_completed_remaining(
     (completed, remaining)
 }
 
-//* Expects raw resource list pasted from crafting calc → https://dune.geno.gg/calculator/
-async fn parse_resources(ctx: &Context<'_>, input: &str) -> Result<String, BotError> {
-    let re = Regex::new(r"(?<amount>[0-9]+)(?<name>\s+([A-Za-z]+\s*)+)").unwrap();
+// Match listed items to the catalog, returning the lines to keep, a preview and how
+// many names aren't in the catalog (they're kept, flagged, until fixed before finishing)
+fn resolve_listed(
+    catalog: &Catalog,
+    mut listed: Vec<ListedResource>,
+) -> (ResourceLines, String, usize) {
+    let mut preview = Vec::new();
+    let mut unresolved = 0;
+    for item in &mut listed {
+        let line = match catalog.find(&item.name) {
+            NameMatch::Exact(name) => {
+                item.name = name;
+                item.describe()
+            }
+            NameMatch::Corrected(name) => {
+                let typed = std::mem::replace(&mut item.name, name);
+                format!("{} (from '{}')", item.describe(), typed)
+            }
+            NameMatch::Unknown(suggestions) => {
+                unresolved += 1;
+                format!("⚠️ {} — {}", item.describe(), suggestion_hint(&suggestions))
+            }
+        };
+        preview.push(format!("• {}", line));
+    }
+    let converted: ResourceLines = listed
+        .iter()
+        .filter(|item| item.amount > 0)
+        .map(|item| (item.amount, item.name.clone()))
+        .collect();
+    (
+        merge_lines(catalog, converted),
+        preview.join("\n"),
+        unresolved,
+    )
+}
 
-    // Sanitize input...
-    let sanitized_list = input
-        .replace(",", "")
-        .replace(" x ", " ")
-        .replace("-", "")
-        .replace("•", "")
-        .replace(":", "");
+// A request has one line per resource (stores match lines on request and resource), so
+// lines naming the same resource, even through an alias, are added up into the first
+fn merge_lines(catalog: &Catalog, lines: ResourceLines) -> ResourceLines {
+    let mut merged: ResourceLines = Vec::new();
+    for (amount, name) in lines {
+        let name = catalog.resolve(&name).unwrap_or(name);
+        let key = normalize_resource_key(&name);
+        match merged
+            .iter_mut()
+            .find(|(_, existing)| normalize_resource_key(existing) == key)
+        {
+            Some((total, _)) => *total += amount,
+            None => merged.push((amount, name)),
+        }
+    }
+    merged
+}
 
-    // ...and parse input into "<amount>,<name>" pairs
-    let mut parsed_items: Vec<(u64, String)> = Vec::new();
-    for caps in re.captures_iter(&sanitized_list) {
-        let amt = caps["amount"].parse::<u64>()?;
-        let name = caps.name("name").unwrap().as_str().trim().to_lowercase();
-        parsed_items.push((amt, name));
+// Catalog spelling of a single typed resource, so aliases and near-misses land on the
+// same line as the resource itself
+fn catalog_name(catalog: &Catalog, resource: &str) -> Result<String, BotError> {
+    let name = normalize_resource_key(resource);
+    if name.is_empty() {
+        return Err("❌ Please name a resource.".into());
     }
+    match catalog.find(&name) {
+        NameMatch::Exact(name) | NameMatch::Corrected(name) => Ok(name),
+        NameMatch::Unknown(suggestions) => {
+            Err(unknown_resource_message(resource, &suggestions).into())
+        }
+    }
+}
 
-    // Convert any water → corpse, rounding down any fraction of corpse
-    const WATER_PER_CORPSE: u64 = 45_000;
-    let converted: Vec<(u64, String)> = parsed_items
-        .iter()
-        .filter_map(|&(amt, ref name)| {
-            if name == "water" {
-                let corpses = amt / WATER_PER_CORPSE;
-                if corpses > 0 {
-                    Some((corpses, "corpse".to_string()))
-                } else {
-                    None
-                }
-            } else {
-                Some((amt, name.clone()))
-            }
-        })
-        .collect();
+// Put listed items into the author's draft, returning the preview and unresolved count
+async fn parse_resources(
+    ctx: &Context<'_>,
+    listed: Vec<ListedResource>,
+) -> Result<(String, usize), BotError> {
+    let (converted, preview, unresolved) = resolve_listed(&ctx.data().catalog, listed);
 
-    // Stash request info into the bot's active memory
-    let user: UserId = ctx.author().id;
-    let mut entry = IN_FLIGHT
-        .remove(&user)
-        .ok_or("❌ You have no active request. Start with `/request start`.")?
-        .1;
-    entry.resources = converted.clone();
-    IN_FLIGHT.insert(user, entry);
-
-    // Build preview text for the user before confirming via /request finish
-    let body = parsed_items
-        .iter()
-        .map(|(amount, name)| {
-            if name == "water" {
-                let corpses = amount / WATER_PER_CORPSE;
-                format!("• Converted: {} x water → {} x corpse", amount, corpses)
-            } else {
-                format!("• {} x {}", amount, name)
-            }
-        })
-        .collect::<Vec<_>>()
-        .join("\n")
-        .trim_end_matches(",")
-        .to_string();
+    // Stash request info into the author's draft
+    let mut entry = drafts::get(ctx.author().id)
+        .ok_or("❌ You have no active request. Start with `/request start`.")?;
+    entry.resources = converted;
+    entry.breakdown = None;
+    drafts::save(entry)?;
 
-    Ok(body)
+    Ok((preview, unresolved))
+}
+
+// Uploaded plans bigger than this are refused before downloading
+const MAX_PLAN_BYTES: u32 = 1024 * 1024;
+
+// Download an uploaded build plan (from Discord's own CDN) and decode it
+async fn read_build_plan(file: &serenity::Attachment) -> Result<BuildPlan, BotError> {
+    if file.size > MAX_PLAN_BYTES {
+        return Err(format!(
+            "❌ {} is too big ({} KB); build plans can be at most {} KB.",
+            file.filename,
+            file.size / 1024,
+            MAX_PLAN_BYTES / 1024
+        )
+        .into());
+    }
+    let data = file.download().await?;
+    parse_build_plan(&file.filename, &data)
+}
+
+// What to do after filling a draft, warning about names the catalog doesn't know
+fn fill_next_step(unresolved: usize) -> String {
+    if unresolved == 0 {
+        "Now finalize your request with `/request finish`.".to_string()
+    } else {
+        format!(
+            "⚠️ {} name(s) aren't in the resource catalog. Fix them with `/request remove` and `/request add` before `/request finish`.",
+            unresolved
+        )
+    }
 }
 
 #[poise::command(
     slash_command,
-    subcommands("start", "bulk_add", "update", "finish"),
+    subcommands(
+        "start", "import", "bulk_add", "add", "remove", "set", "expand", "update", "cancel",
+        "finish", "queue", "priority", "stock", "claim", "assign", "close", "reopen", "edit"
+    ),
     subcommand_required
 )]
 pub async fn request(_: Context<'_>) -> Result<(), BotError> {
     Ok(())
 }
 
+// Products of the recipe catalog; anything else still works as a free-text title
+async fn product_autocomplete<'a>(ctx: Context<'a>, partial: &str) -> Vec<AutocompleteChoice> {
+    ctx.data()
+        .recipes
+        .search(partial)
+        .into_iter()
+        .take(25)
+        .map(|name| AutocompleteChoice::new(name, name))
+        .collect()
+}
+
 #[poise::command(slash_command)]
 pub async fn start(
     ctx: Context<'_>,
-    #[description = "Title for the request"] product: String,
+    #[description = "Product to craft (fills in its materials), or a title for the request"]
+    #[autocomplete = "product_autocomplete"]
+    product: String,
+    #[description = "How many to craft (default 1)"]
+    #[min = 1]
+    quantity: Option<u32>,
 ) -> Result<(), BotError> {
-    dotenv().ok();
     ctx.defer().await?;
     let user = ctx.author().id;
 
     // Restrict the user to one in-progress request at a time
-    if IN_FLIGHT.contains_key(&user) {
-        ctx.say("❌ You already have a pending request. Please finish it with `/request finish` before starting a new one.")
+    if drafts::contains(user) {
+        ctx.say("❌ You already have a pending request. Please finish it with `/request finish` (or drop it with `/request cancel`) before starting a new one.")
             .await?;
         return Ok(());
     }
 
-    let confirmation = ctx.send(
-    poise::CreateReply::default()
-            .content(format!(
-                "✅ Request started for **{}**.\n\
-                Now add resources with `/request bulk_add`, then finalize with `/request finish`.",
-                product
-            ))
-            .reply(true) // <- make sure this replies in channel
-    ).await?;
+    // A catalog product brings its materials along; anything else starts empty
+    let quantity = u64::from(quantity.unwrap_or(1));
+    let (product, resources, next_step) = match ctx.data().recipes.get(&product) {
+        Some(recipe) => {
+            let resources = recipe.materials_for(quantity)?;
+            let title = if quantity > 1 {
+                format!("{} x {}", quantity, recipe.product)
+            } else {
+                recipe.product.clone()
+            };
+            let lines = resources
+                .iter()
+                .map(|(amt, name)| format!("• {} x {}", amt, name))
+                .collect::<Vec<_>>()
+                .join("\n");
+            let next_step = format!(
+                "Materials from the recipe:\n```{}```\n\
+                Adjust them with `/request add`, `/request remove` or `/request set`, then finalize with `/request finish`.",
+                lines
+            );
+            (title, resources, next_step)
+        }
+        None => (
+            product,
+            Vec::new(),
+            "Now add resources with `/request bulk_add`, then finalize with `/request finish`."
+                .to_string(),
+        ),
+    };
+
+    let confirmation = ctx
+        .send(
+            poise::CreateReply::default()
+                .content(format!(
+                    "✅ Request started for **{}**.\n{}",
+                    product, next_step
+                ))
+                .reply(true), // <- make sure this replies in channel
+        )
+        .await?;
 
     let message_id = confirmation.message().await?.id;
 
-    IN_FLIGHT.insert(
-        user,
-        InProgressRequest {
-            product: product.clone(),
-            resources: Vec::new(),
-            _sheet_row_ids: Vec::new(),
-            _message_id: message_id,
-        },
-    );
+    let now = Utc::now();
+    drafts::save(InProgressRequest {
+        author: user,
+        channel_id: ctx.channel_id(),
+        product,
+        resources,
+        breakdown: None,
+        message_id,
+        started_at: now,
+        updated_at: now,
+    })?;
 
     Ok(())
 }
 
+/// Fill the draft from a pasted list or an uploaded plan (JSON, CSV or text)
 #[poise::command(slash_command)]
 pub async fn bulk_add(
     ctx: Context<'_>,
-    #[description = "Paste the raw resource list here"] raw_resource_list: String,
+    #[description = "Paste the raw resource list here"] raw_resource_list: Option<String>,
+    #[description = "Or upload a build plan export (JSON, CSV or text)"] file: Option<
+        serenity::Attachment,
+    >,
 ) -> Result<(), BotError> {
+    if raw_resource_list.is_none() && file.is_none() {
+        return Err("❌ Paste a resource list or upload a build plan file.".into());
+    }
     ctx.defer().await?;
-    let preview: String = parse_resources(&ctx, &raw_resource_list).await?;
-    let user = ctx.author().id; //? can we refactor this out? not critical...
-    let _entry = IN_FLIGHT
-        .get(&user) //? not sure where/how this "entry" is being used
-        .ok_or("❌ Could not find in-flight request after parsing.")?;
+    // Both together are read as one list, pasted lines first
+    let mut items = raw_resource_list
+        .as_deref()
+        .map(parse_material_list)
+        .unwrap_or_default();
+    if let Some(file) = &file {
+        items.extend(read_build_plan(file).await?.materials);
+    }
+    let (conversions, catalog) = (&ctx.data().config.conversions, &ctx.data().catalog);
+    let listed = listed_resources(items, conversions, catalog);
+    let (preview, unresolved) = parse_resources(&ctx, listed).await?;
 
     ctx.send(
-    poise::CreateReply::default()
+        poise::CreateReply::default()
             .content(format!(
-                "✅ Resources recorded.\n```{}```\nNow finalize your request with `/request finish`.",
-                preview
+                "✅ Resources recorded.\n{}\n{}",
+                code_block(&preview),
+                fill_next_step(unresolved)
             ))
-            .reply(true)
+            .reply(true),
     )
     .await?;
     Ok(())
 }
 
+/// Start a request from an uploaded build plan (JSON, CSV or text export)
 #[poise::command(slash_command)]
-pub async fn update(ctx: Context<'_>) -> Result<(), BotError> {
-    let inventory: HashMap<String, u64> = load_inventory_from_sheets().await?;
+pub async fn import(
+    ctx: Context<'_>,
+    #[description = "Build plan export (JSON, CSV or text)"] file: serenity::Attachment,
+    #[description = "Title for the request (default: the plan's own, or the file name)"]
+    product: Option<String>,
+) -> Result<(), BotError> {
+    ctx.defer().await?;
     let user = ctx.author().id;
-    let entry = IN_FLIGHT
-        .get(&user)
+    if drafts::contains(user) {
+        ctx.say("❌ You already have a pending request. Please finish it with `/request finish` (or drop it with `/request cancel`) before starting a new one.")
+            .await?;
+        return Ok(());
+    }
+
+    let plan = read_build_plan(&file).await?;
+    let product = product
+        .or(plan.title)
+        .map(|title| title.trim().to_string())
+        .filter(|title| !title.is_empty())
+        .unwrap_or_else(|| {
+            file.filename
+                .rsplit_once('.')
+                .map_or(file.filename.as_str(), |(stem, _)| stem)
+                .to_string()
+        });
+    let (conversions, catalog) = (&ctx.data().config.conversions, &ctx.data().catalog);
+    let listed = listed_resources(plan.materials, conversions, catalog);
+    let (resources, preview, unresolved) = resolve_listed(catalog, listed);
+
+    let confirmation = ctx
+        .send(
+            poise::CreateReply::default()
+                .content(format!(
+                    "✅ Request started for **{}** from {}.\n{}\n{}",
+                    product,
+                    file.filename,
+                    code_block(&preview),
+                    fill_next_step(unresolved)
+                ))
+                .reply(true),
+        )
+        .await?;
+    let message_id = confirmation.message().await?.id;
+
+    let now = Utc::now();
+    drafts::save(InProgressRequest {
+        author: user,
+        channel_id: ctx.channel_id(),
+        product,
+        resources,
+        breakdown: None,
+        message_id,
+        started_at: now,
+        updated_at: now,
+    })?;
+    Ok(())
+}
+
+// Lines of the author's draft, for `/request remove` and `/request set`
+async fn draft_resource_autocomplete<'a>(
+    ctx: Context<'a>,
+    partial: &str,
+) -> Vec<AutocompleteChoice> {
+    let Some(entry) = drafts::get(ctx.author().id) else {
+        return Vec::new();
+    };
+    entry
+        .resources
+        .iter()
+        .filter(|(_, name)| name.to_lowercase().contains(&partial.to_lowercase()))
+        .take(25)
+        .map(|(_, name)| AutocompleteChoice::new(name.clone(), name.clone()))
+        .collect()
+}
+
+// Apply `edit` to the author's draft, save it and reply with the resulting lines
+async fn edit_draft(
+    ctx: Context<'_>,
+    edit: impl FnOnce(&mut ResourceLines) -> Result<String, BotError>,
+) -> Result<(), BotError> {
+    let mut entry = drafts::get(ctx.author().id)
+        .ok_or("❌ You have no active request. Start one with `/request start`.")?;
+    let summary = edit(&mut entry.resources)?;
+
+    let lines = if entry.resources.is_empty() {
+        "(no resources yet)".to_string()
+    } else {
+        entry
+            .resources
+            .iter()
+            .map(|(amt, name)| format!("• {} x {}", amt, name))
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+    let product = entry.product.clone();
+    drafts::save(entry)?;
+
+    ctx.say(format!("{}\n**{}**\n```{}```", summary, product, lines))
+        .await?;
+    Ok(())
+}
+
+/// Add to a line of the draft, creating it if the resource isn't listed yet
+#[poise::command(slash_command)]
+pub async fn add(
+    ctx: Context<'_>,
+    #[description = "Amount to add"]
+    #[min = 1]
+    amount: u32,
+    #[description = "Resource to add"]
+    #[autocomplete = "resource_autocomplete"]
+    resource: String,
+) -> Result<(), BotError> {
+    let catalog = &ctx.data().catalog;
+    let name = catalog_name(catalog, &resource)?;
+    let key = normalize_resource_key(&name);
+    edit_draft(ctx, |resources| {
+        *resources = merge_lines(catalog, std::mem::take(resources));
+        match resources
+            .iter_mut()
+            .find(|(_, n)| normalize_resource_key(n) == key)
+        {
+            Some((amt, _)) => *amt += u64::from(amount),
+            None => resources.push((u64::from(amount), name.clone())),
+        }
+        Ok(format!("✅ Added {} x {}.", amount, name))
+    })
+    .await
+}
+
+/// Drop a line from the draft
+#[poise::command(slash_command)]
+pub async fn remove(
+    ctx: Context<'_>,
+    #[description = "Resource to remove"]
+    #[autocomplete = "draft_resource_autocomplete"]
+    resource: String,
+) -> Result<(), BotError> {
+    let key = normalize_resource_key(&resource);
+    edit_draft(ctx, |resources| {
+        let idx = resources
+            .iter()
+            .position(|(_, n)| normalize_resource_key(n) == key)
+            .ok_or_else(|| format!("❌ '{}' isn't in your request.", resource))?;
+        let (_, name) = resources.remove(idx);
+        resources.retain(|(_, n)| normalize_resource_key(n) != key);
+        Ok(format!("✅ Removed {}.", name))
+    })
+    .await
+}
+
+/// Replace the amount of a line in the draft (adding it if missing)
+#[poise::command(slash_command)]
+pub async fn set(
+    ctx: Context<'_>,
+    #[description = "Resource to change"]
+    #[autocomplete = "draft_resource_autocomplete"]
+    resource: String,
+    #[description = "New amount"]
+    #[min = 1]
+    amount: u32,
+) -> Result<(), BotError> {
+    let catalog = &ctx.data().catalog;
+    let name = catalog_name(catalog, &resource)?;
+    let key = normalize_resource_key(&name);
+    edit_draft(ctx, |resources| {
+        *resources = merge_lines(catalog, std::mem::take(resources));
+        match resources
+            .iter_mut()
+            .find(|(_, n)| normalize_resource_key(n) == key)
+        {
+            Some((amt, _)) => *amt = u64::from(amount),
+            None => resources.push((u64::from(amount), name.clone())),
+        }
+        Ok(format!("✅ {} set to {}.", name, amount))
+    })
+    .await
+}
+
+// Discord rejects messages over 2000 characters
+fn code_block(text: &str) -> String {
+    const MAX: usize = 1800;
+    let text = match text.char_indices().nth(MAX) {
+        Some((cut, _)) => format!("{}…", &text[..cut]),
+        None => text.to_string(),
+    };
+    format!("```{}```", text)
+}
+
+/// Break products with a recipe down to the raw materials still to gather
+#[poise::command(slash_command)]
+pub async fn expand(ctx: Context<'_>) -> Result<(), BotError> {
+    ctx.defer().await?;
+    let mut entry = drafts::get(ctx.author().id)
+        .ok_or("❌ You have no active request. Start one with `/request start`.")?;
+    let recipes = &ctx.data().recipes;
+    if !entry
+        .resources
+        .iter()
+        .any(|(_, name)| recipes.get(name).is_some())
+    {
+        return Err("❌ Nothing in your request has a recipe to break down.".into());
+    }
+
+    let store = &ctx.data().store;
+    let inventory = available_inventory(&store.inventory().await?, &store.request_rows().await?);
+    let breakdown = recipes.expand(&entry.resources, &inventory)?;
+    entry.resources = breakdown.lines;
+    entry.breakdown = Some(breakdown.tree.clone());
+    drafts::save(entry)?;
+
+    ctx.say(format!(
+        "🌳 Crafting tree (intermediates in stock are used first):\n{}\n\
+        Your request now lists what's left to gather. Finalize it with `/request finish`.",
+        code_block(&breakdown.tree)
+    ))
+    .await?;
+    Ok(())
+}
+
+/// Throw away the draft without posting it
+#[poise::command(slash_command)]
+pub async fn cancel(ctx: Context<'_>) -> Result<(), BotError> {
+    let entry = drafts::take(ctx.author().id)?.ok_or("❌ You have no active request to cancel.")?;
+    ctx.say(format!(
+        "🗑️ Draft request for **{}** cancelled.",
+        entry.product
+    ))
+    .await?;
+    Ok(())
+}
+
+#[poise::command(slash_command)]
+pub async fn update(ctx: Context<'_>) -> Result<(), BotError> {
+    // Stock already reserved by finished requests isn't coming to this one
+    let store = &ctx.data().store;
+    let inventory: HashMap<String, u64> =
+        available_inventory(&store.inventory().await?, &store.request_rows().await?);
+    let entry = drafts::get(ctx.author().id)
         .ok_or("❌ You have no active request. Start one with `/request start`.")?;
 
     let embed = build_update_embed(&entry.product, &entry.resources, &inventory);
@@ -248,35 +596,211 @@ pub async fn update(ctx: Context<'_>) -> Result<(), BotError> {
     Ok(())
 }
 
+/// Stock on hand, how much of it open requests have reserved and what's still free
+#[poise::command(slash_command)]
+pub async fn stock(
+    ctx: Context<'_>,
+    #[description = "Show which requests hold this resource"]
+    #[autocomplete = "resource_autocomplete"]
+    resource: Option<String>,
+) -> Result<(), BotError> {
+    let store = &ctx.data().store;
+    let catalog = &ctx.data().catalog;
+    let inventory = store.inventory().await?;
+    let open: Vec<RequestRow> = store
+        .request_rows()
+        .await?
+        .into_iter()
+        .filter(|row| row.status == RequestStatus::InProgress)
+        .collect();
+    let available = available_inventory(&inventory, &open);
+    let count = |stock: &HashMap<String, u64>, key: &str| stock.get(key).copied().unwrap_or(0);
+
+    let embed = match resource {
+        Some(resource) => {
+            let key = normalize_resource_key(&resource);
+            let holders = open
+                .iter()
+                .filter(|row| normalize_resource_key(&row.resource) == key)
+                .map(|row| {
+                    format!(
+                        "• {}: {} of {} reserved",
+                        row.product, row.reserved, row.amount
+                    )
+                })
+                .collect::<Vec<_>>();
+            CreateEmbed::new()
+                .title(format!("📦 {}", catalog.display_name(&key)))
+                .field("In stock", count(&inventory, &key).to_string(), true)
+                .field("Available", count(&available, &key).to_string(), true)
+                .field(
+                    "Open requests",
+                    if holders.is_empty() {
+                        "None".to_string()
+                    } else {
+                        holders.join("\n")
+                    },
+                    false,
+                )
+        }
+        None => {
+            // Only what open requests want; the full inventory is in the sheet
+            let lines = resource_keys(&open)
+                .iter()
+                .map(|key| {
+                    let stock = count(&inventory, key);
+                    let free = count(&available, key);
+                    format!(
+                        "• {}: {} in stock, {} reserved, {} available",
+                        catalog.display_name(key),
+                        stock,
+                        stock - free,
+                        free
+                    )
+                })
+                .collect::<Vec<_>>();
+            CreateEmbed::new()
+                .title("📦 Reserved stock")
+                .description(if lines.is_empty() {
+                    "No open request needs anything.".to_string()
+                } else {
+                    lines.join("\n")
+                })
+        }
+    };
+
+    ctx.send(CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
+
+/// Open requests, in the order incoming stock is reserved for them
+#[poise::command(slash_command)]
+pub async fn queue(ctx: Context<'_>) -> Result<(), BotError> {
+    let rows = ctx.data().store.request_rows().await?;
+
+    // One entry per request, in the order it was first stored
+    let mut requests: Vec<(usize, &RequestRow, u64, u64)> = Vec::new();
+    for (idx, row) in rows.iter().enumerate() {
+        if row.status != RequestStatus::InProgress {
+            continue;
+        }
+        match requests
+            .iter_mut()
+            .find(|(_, first, _, _)| first.request_id == row.request_id)
+        {
+            Some((_, _, reserved, needed)) => {
+                *reserved += row.reserved;
+                *needed += row.amount;
+            }
+            None => requests.push((idx, row, row.reserved, row.amount)),
+        }
+    }
+    requests.sort_by_key(|(idx, row, _, _)| (Reverse(row.priority), *idx));
+
+    let now = Utc::now();
+    let lines = requests
+        .iter()
+        .enumerate()
+        .map(|(position, (_, row, reserved, needed))| {
+            let due = match row.due {
+                Some(due) if due < now => {
+                    format!(", ⚠️ **overdue** since <t:{}:R>", due.timestamp())
+                }
+                Some(due) => format!(", due <t:{}:R>", due.timestamp()),
+                None => String::new(),
+            };
+            format!(
+                "{}. **{}** ({}{}) <#{}>: {} of {} reserved",
+                position + 1,
+                row.product,
+                row.priority.as_str(),
+                due,
+                row.thread_id,
+                reserved,
+                needed
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let embed = CreateEmbed::new()
+        .title("📋 Request queue")
+        .description(if lines.is_empty() {
+            "No open requests.".to_string()
+        } else {
+            lines.join("\n")
+        });
+    ctx.send(CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
+
+/// Change where the request of this thread sits in the queue
+#[poise::command(slash_command, check = "is_officer")]
+pub async fn priority(
+    ctx: Context<'_>,
+    #[description = "New priority"] priority: RequestPriority,
+) -> Result<(), BotError> {
+    let lines = thread_request(ctx).await?;
+    let store = ctx.data().store.as_ref();
+    let _guard = ALLOCATION_LOCK.lock().await;
+    let mut lines = store.request_lines(&lines[0].request_id).await?;
+    let previous = lines[0].priority;
+    for line in lines.iter_mut() {
+        line.priority = priority;
+    }
+    store.update_request_rows(&lines).await?;
+
+    ctx.say(format!(
+        "📌 {} is now {} priority (was {}).",
+        lines[0].product,
+        priority.as_str(),
+        previous.as_str()
+    ))
+    .await?;
+    Ok(())
+}
+
 #[poise::command(slash_command)]
-pub async fn finish(ctx: Context<'_>) -> Result<(), BotError> {
-    let service_account_key = read_service_account_key(SERVICE_ACCOUNT_PATH)
-        .await
-        .expect("Can't read credential, an error occurred");
-    let authenticator = ServiceAccountAuthenticator::builder(service_account_key)
-        .build()
-        .await
-        .expect("failed to create authenticator");
-    let executor = TokioExecutor::new();
-    let https_connector = HttpsConnectorBuilder::new()
-        .with_native_roots()
-        .unwrap()
-        .https_or_http()
-        .enable_http1()
-        .build();
-    let client = LegacyClient::builder(executor).build(https_connector);
-    let hub = Sheets::new(client, authenticator);
+pub async fn finish(
+    ctx: Context<'_>,
+    #[description = "How soon it should get stock (default normal)"] priority: Option<
+        RequestPriority,
+    >,
+    #[description = "When it's needed by: YYYY-MM-DD or YYYY-MM-DD HH:MM (UTC)"] due: Option<
+        String,
+    >,
+) -> Result<(), BotError> {
     let user = ctx.author().id;
+    let due = due.as_deref().map(parse_due).transpose()?;
 
     // Post in a pre-defined channel specific for request threads
-    let target_channel_id: ChannelId = var("REQUESTS_CHANNEL_ID")?.parse::<u64>()?.into();
+    let target_channel_id: ChannelId = ctx.data().config.requests_channel_id;
+
+    // Only dropped once the request is posted, so a failure here keeps the draft
+    let entry =
+        drafts::get(user).ok_or("You have no active request. Start one with `/request start`.")?;
 
-    let entry = IN_FLIGHT
-        .remove(&user)
-        .ok_or("You have no active request. Start one with `/request start`.")?
-        .1;
+    // Lines the catalog doesn't know could never be matched with stock
+    let unknown: Vec<String> = entry
+        .resources
+        .iter()
+        .filter_map(|(_, name)| match ctx.data().catalog.find(name) {
+            NameMatch::Exact(_) => None,
+            NameMatch::Corrected(guess) => Some(format!("• {} (did you mean {}?)", name, guess)),
+            NameMatch::Unknown(suggestions) => {
+                Some(format!("• {} — {}", name, suggestion_hint(&suggestions)))
+            }
+        })
+        .collect();
+    if !unknown.is_empty() {
+        return Err(format!(
+            "❌ Some lines aren't resources from the catalog:\n{}\nFix them with `/request remove` and `/request add`, then finish again.",
+            unknown.join("\n")
+        )
+        .into());
+    }
 
-    let resources = entry.resources.clone();
+    // Drafts saved before lines were merged may still name a resource twice
+    let resources = merge_lines(&ctx.data().catalog, entry.resources.clone());
     // Unique identifier for each request
     let request_id = Uuid::new_v4().to_string();
 
@@ -289,9 +813,12 @@ pub async fn finish(ctx: Context<'_>) -> Result<(), BotError> {
         .collect::<Vec<_>>()
         .join("\n");
 
-    let embed = CreateEmbed::new()
+    let mut embed = CreateEmbed::new()
         .title(format!("🔷 CRAFTING REQUEST: {}", entry.product))
         .field("🛠️ Request Materials:", request_text, false);
+    if let Some(due) = due {
+        embed = embed.field("📅 Due:", format!("<t:{}:F>", due.timestamp()), false);
+    }
 
     let msg_builder = CreateMessage::new().embed(embed.clone());
 
@@ -303,34 +830,31 @@ pub async fn finish(ctx: Context<'_>) -> Result<(), BotError> {
         .create_thread_from_message(&ctx.http(), post.id, thread_builder)
         .await?;
 
-    let mut values = vec![];
-    for (req_amt, name) in &resources {
-        values.push(vec![
-            request_id.clone().into(),
-            entry.product.clone().into(),
-            name.clone().into(),
-            req_amt.to_string().into(),
-            "in_progress".into(),
-            thread.id.to_string().into(),
-        ]);
-    }
-
-    let request_range = "Sheet1!A:F";
-    let request_spreadsheet_id = var("SPREADSHEET_ID_REQUEST")?;
-
-    hub.spreadsheets()
-        .values_append(
-            ValueRange {
-                range: Some(request_range.to_string()),
-                values: Some(values),
-                ..Default::default()
-            },
-            &request_spreadsheet_id,
-            request_range,
-        )
-        .value_input_option("RAW")
-        .doit()
-        .await?;
+    let rows: Vec<RequestRow> = resources
+        .iter()
+        .map(|(req_amt, name)| RequestRow {
+            request_id: request_id.clone(),
+            product: entry.product.clone(),
+            resource: name.clone(),
+            amount: *req_amt,
+            reserved: 0,
+            status: RequestStatus::InProgress,
+            thread_id: thread.id,
+            requester: Some(user),
+            priority: priority.unwrap_or_default(),
+            due,
+            last_reminder: None,
+            crafter: None,
+            claims: Vec::new(),
+        })
+        .collect();
+    {
+        // Set aside whatever of it is already in stock and free
+        let _guard = ALLOCATION_LOCK.lock().await;
+        let store = ctx.data().store.as_ref();
+        store.append_request_rows(&rows).await?;
+        rebalance_store(store, &resource_keys(&rows), None).await?;
+    }
 
     // Send basic welcome message in the thread with instructions for the user
     // TODO: Allow for adjustments to welcome message or request notes
@@ -341,12 +865,23 @@ pub async fn finish(ctx: Context<'_>) -> Result<(), BotError> {
     );
     let _ = thread.send_message(&ctx.http(), info_builder).await?;
 
+    if let Some(tree) = &entry.breakdown {
+        let tree_message =
+            CreateMessage::new().content(format!("🌳 Crafting tree:\n{}", code_block(tree)));
+        let _ = thread.send_message(&ctx.http(), tree_message).await?;
+    }
+
     let new_thread_message = CreateMessage::new()
         .button(
             CreateButton::new(format!("request_update:{request_id}"))
                 .label("Update")
                 .style(ButtonStyle::Primary),
         )
+        .button(
+            CreateButton::new(format!("request_contribute:{request_id}"))
+                .label("Contribute")
+                .style(ButtonStyle::Secondary),
+        )
         .button(
             CreateButton::new(format!("request_complete:{request_id}"))
                 .label("Complete")
@@ -354,5 +889,670 @@ pub async fn finish(ctx: Context<'_>) -> Result<(), BotError> {
         );
 
     let _ = thread.send_message(&ctx.http(), new_thread_message).await?;
+    drafts::take(user)?;
     Ok(())
 }
+
+pub enum CompletionOutcome {
+    Completed {
+        product: String,
+        thread_id: ChannelId,
+    },
+    Insufficient,
+    /// Already completed or cancelled
+    NotOpen,
+}
+
+// Normalized names of the resources `lines` need, each once
+fn resource_keys(lines: &[RequestRow]) -> Vec<String> {
+    let mut keys: Vec<String> = lines
+        .iter()
+        .map(|line| normalize_resource_key(&line.resource))
+        .collect();
+    keys.sort();
+    keys.dedup();
+    keys
+}
+
+/// Consume a request's materials from the inventory and mark it completed, provided
+/// each line is covered by its reservation plus stock nobody else has reserved
+pub async fn finalize_request(
+    store: &dyn LedgerStore,
+    request_id: &str,
+) -> Result<CompletionOutcome, BotError> {
+    let _guard = ALLOCATION_LOCK.lock().await;
+    let mut lines = store.request_lines(request_id).await?;
+    let first = lines.first().ok_or("No thread ID found for request")?;
+    let (product, thread_id) = (first.product.clone(), first.thread_id);
+    if first.status != RequestStatus::InProgress {
+        return Ok(CompletionOutcome::NotOpen);
+    }
+
+    let available = available_inventory(&store.inventory().await?, &store.request_rows().await?);
+    let mut deltas = Vec::new();
+    for line in &lines {
+        let key = normalize_resource_key(&line.resource);
+        if line.reserved + available.get(&key).copied().unwrap_or(0) < line.amount {
+            return Ok(CompletionOutcome::Insufficient);
+        }
+        deltas.push((key, -i64::try_from(line.amount)?));
+    }
+    if let InventoryAdjustment::Insufficient(_) = store.adjust_inventory(&deltas).await? {
+        return Ok(CompletionOutcome::Insufficient);
+    }
+
+    for line in lines.iter_mut() {
+        line.status = RequestStatus::Completed;
+        line.reserved = 0;
+    }
+    store.update_request_rows(&lines).await?;
+    rebalance_store(store, &resource_keys(&lines), None).await?;
+
+    Ok(CompletionOutcome::Completed { product, thread_id })
+}
+
+pub async fn complete_request(
+    ctx: &serenity::Context,
+    comp: &ComponentInteraction,
+    store: &dyn LedgerStore,
+    request_id: &str,
+) -> Result<(), BotError> {
+    let (product_name, thread_id) = match finalize_request(store, request_id).await? {
+        CompletionOutcome::Completed { product, thread_id } => (product, thread_id),
+        CompletionOutcome::Insufficient => {
+            comp.channel_id
+                .send_message(
+                    &ctx.http,
+                    CreateMessage::new()
+                        .content("❌ Not enough resources in inventory to complete this request."),
+                )
+                .await?;
+            return Ok(());
+        }
+        CompletionOutcome::NotOpen => {
+            comp.channel_id
+                .send_message(
+                    &ctx.http,
+                    CreateMessage::new().content("❌ This request is no longer open."),
+                )
+                .await?;
+            return Ok(());
+        }
+    };
+
+    // Thank everyone whose submissions went into it
+    let mut credits: Vec<(String, Vec<String>)> = Vec::new();
+    for (user, resource, amount) in contributions(&store.ledger_entries().await?, request_id) {
+        let brought = format!("{} x {}", amount, resource);
+        match credits.iter_mut().find(|(who, _)| *who == user) {
+            Some((_, items)) => items.push(brought),
+            None => credits.push((user, vec![brought])),
+        }
+    }
+    let credits: Vec<String> = credits
+        .into_iter()
+        .map(|(user, items)| format!("• {}: {}", user, items.join(", ")))
+        .collect();
+
+    let embed = CreateEmbed::new()
+        .title("✅ CRAFTING COMPLETE")
+        .description(format!(
+            "{} is complete. All materials have been submitted.",
+            product_name,
+        ))
+        .field(
+            "🙏 Contributors:",
+            field_text(&credits, "No submissions were recorded for this request."),
+            false,
+        )
+        .color(0x00ff00);
+
+    thread_id
+        .send_message(&ctx.http, CreateMessage::new().embed(embed))
+        .await?;
+
+    thread_id
+        .edit_thread(&ctx.http, EditThread::default().locked(true))
+        .await?;
+    Ok(())
+}
+
+// The request whose thread the command was run in
+async fn thread_request(ctx: Context<'_>) -> Result<Vec<RequestRow>, BotError> {
+    let thread_id = ctx.channel_id();
+    let lines: Vec<RequestRow> = ctx
+        .data()
+        .store
+        .request_rows()
+        .await?
+        .into_iter()
+        .filter(|row| row.thread_id == thread_id)
+        .collect();
+    if lines.is_empty() {
+        return Err("❌ Run this command inside the thread of a crafting request.".into());
+    }
+    Ok(lines)
+}
+
+// Finalized requests can be changed by whoever made them, or by an officer
+async fn ensure_can_manage(ctx: Context<'_>, lines: &[RequestRow]) -> Result<(), BotError> {
+    let is_requester = lines
+        .first()
+        .is_some_and(|line| line.requester == Some(ctx.author().id));
+    if is_requester || author_is_officer(ctx).await {
+        Ok(())
+    } else {
+        Err("❌ Only the requester or an officer can change this request.".into())
+    }
+}
+
+// Closing a request frees its reservations; reopening it lets it reserve again. Only
+// open requests can be closed and only finished ones reopened, which is checked under
+// the lock since a completion or another close may have changed the status meanwhile.
+async fn set_status(
+    ctx: Context<'_>,
+    request_id: &str,
+    status: RequestStatus,
+) -> Result<(), BotError> {
+    let store = ctx.data().store.as_ref();
+    let _guard = ALLOCATION_LOCK.lock().await;
+    let mut lines = store.request_lines(request_id).await?;
+    let current = lines
+        .first()
+        .ok_or("❌ This request no longer exists.")?
+        .status;
+    match (current, status) {
+        (RequestStatus::InProgress, RequestStatus::InProgress) => {
+            return Err("❌ This request is already open.".into());
+        }
+        (RequestStatus::InProgress, _) | (_, RequestStatus::InProgress) => {}
+        _ => return Err("❌ This request is no longer open.".into()),
+    }
+    for line in lines.iter_mut() {
+        line.status = status;
+    }
+    store.update_request_rows(&lines).await?;
+    rebalance_store(store, &resource_keys(&lines), None).await?;
+    Ok(())
+}
+
+/// Cancel the request of this thread and archive the thread
+#[poise::command(slash_command)]
+pub async fn close(
+    ctx: Context<'_>,
+    #[description = "Why the request is being dropped"] reason: Option<String>,
+) -> Result<(), BotError> {
+    let lines = thread_request(ctx).await?;
+    ensure_can_manage(ctx, &lines).await?;
+    set_status(ctx, &lines[0].request_id, RequestStatus::Cancelled).await?;
+
+    let embed = CreateEmbed::new()
+        .title("🚫 REQUEST CANCELLED")
+        .description(format!(
+            "{} was cancelled by {}.\n{}",
+            lines[0].product,
+            ctx.author().name,
+            reason.as_deref().unwrap_or("No reason given."),
+        ))
+        .color(0x808080);
+    ctx.send(CreateReply::default().embed(embed)).await?;
+
+    ctx.channel_id()
+        .edit_thread(
+            &ctx.http(),
+            EditThread::default().locked(true).archived(true),
+        )
+        .await?;
+    Ok(())
+}
+
+/// Put a completed or cancelled request back in progress
+#[poise::command(slash_command)]
+pub async fn reopen(ctx: Context<'_>) -> Result<(), BotError> {
+    let lines = thread_request(ctx).await?;
+    ensure_can_manage(ctx, &lines).await?;
+    // Materials a completion took from the inventory stay taken; correct them with `/adjust`
+    set_status(ctx, &lines[0].request_id, RequestStatus::InProgress).await?;
+
+    ctx.channel_id()
+        .edit_thread(
+            &ctx.http(),
+            EditThread::default().locked(false).archived(false),
+        )
+        .await?;
+
+    let embed = CreateEmbed::new()
+        .title(format!("🔁 REQUEST REOPENED: {}", lines[0].product))
+        .description(format!("Reopened by {}.", ctx.author().name));
+    ctx.send(CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
+
+// Lines of the request in the current thread, for `/request edit`
+async fn thread_resource_autocomplete<'a>(
+    ctx: Context<'a>,
+    partial: &str,
+) -> Vec<AutocompleteChoice> {
+    let Ok(lines) = thread_request(ctx).await else {
+        return Vec::new();
+    };
+    lines
+        .iter()
+        .filter(|line| {
+            line.resource
+                .to_lowercase()
+                .contains(&partial.to_lowercase())
+        })
+        .take(25)
+        .map(|line| AutocompleteChoice::new(line.resource.clone(), line.resource.clone()))
+        .collect()
+}
+
+/// Change how much of one material the request in this thread needs
+#[poise::command(slash_command)]
+pub async fn edit(
+    ctx: Context<'_>,
+    #[description = "Material to change"]
+    #[autocomplete = "thread_resource_autocomplete"]
+    resource: String,
+    #[description = "Total amount needed"]
+    #[min = 1]
+    amount: u32,
+) -> Result<(), BotError> {
+    let lines = thread_request(ctx).await?;
+    ensure_can_manage(ctx, &lines).await?;
+
+    let key = normalize_resource_key(&resource);
+    let store = ctx.data().store.as_ref();
+    let _guard = ALLOCATION_LOCK.lock().await;
+    let mut line = store
+        .request_lines(&lines[0].request_id)
+        .await?
+        .into_iter()
+        .find(|line| normalize_resource_key(&line.resource) == key)
+        .ok_or(format!("❌ '{}' isn't part of this request.", resource))?;
+    // Read under the lock, so a completion that just happened is seen
+    if line.status != RequestStatus::InProgress {
+        return Err("❌ Only open requests can be edited. Use `/request reopen` first.".into());
+    }
+    let previous = line.amount;
+    line.amount = u64::from(amount);
+    store
+        .update_request_rows(std::slice::from_ref(&line))
+        .await?;
+    rebalance_store(store, std::slice::from_ref(&key), None).await?;
+
+    ctx.say(format!(
+        "✏️ {} now needs {} x {} (was {}).",
+        line.product, amount, line.resource, previous
+    ))
+    .await?;
+    Ok(())
+}
+
+// `YYYY-MM-DD` (end of that day) or `YYYY-MM-DD HH:MM`, in UTC
+fn parse_due(raw: &str) -> Result<DateTime<Utc>, BotError> {
+    let raw = raw.trim();
+    let due = NaiveDateTime::parse_from_str(raw, "%Y-%m-%d %H:%M")
+        .ok()
+        .or_else(|| {
+            NaiveDate::parse_from_str(raw, "%Y-%m-%d")
+                .ok()
+                .and_then(|date| date.and_hms_opt(23, 59, 0))
+        })
+        .ok_or(format!(
+            "❌ '{}' isn't a date. Use `YYYY-MM-DD` or `YYYY-MM-DD HH:MM` (UTC).",
+            raw
+        ))?
+        .and_utc();
+    if due <= Utc::now() {
+        return Err("❌ The due date has to be in the future.".into());
+    }
+    Ok(due)
+}
+
+/// Post a reminder in the thread of every open request whose due date is within one of
+/// `reminder_hours` (largest first), once per threshold, with what it still needs
+pub async fn send_due_reminders(
+    http: &serenity::Http,
+    store: &dyn LedgerStore,
+    reminder_hours: &[u64],
+) -> Result<(), BotError> {
+    let now = Utc::now();
+    let rows = store.request_rows().await?;
+
+    let mut request_ids: Vec<&str> = Vec::new();
+    for row in &rows {
+        if row.status == RequestStatus::InProgress
+            && row.due.is_some()
+            && !request_ids.contains(&row.request_id.as_str())
+        {
+            request_ids.push(&row.request_id);
+        }
+    }
+
+    for request_id in request_ids {
+        let lines: Vec<RequestRow> = rows
+            .iter()
+            .filter(|row| row.request_id == request_id)
+            .cloned()
+            .collect();
+        let (Some(due), last_reminder) = (lines[0].due, lines[0].last_reminder) else {
+            continue;
+        };
+        let hours_left = (due - now).num_hours();
+        // The smallest threshold already crossed that hasn't been announced yet
+        let Some(threshold) = reminder_hours
+            .iter()
+            .copied()
+            .filter(|&hours| hours_left < hours as i64)
+            .filter(|&hours| last_reminder.is_none_or(|sent| hours < sent))
+            .min()
+        else {
+            continue;
+        };
+
+        // Stock reserved for this request is what counts as collected
+        let resources: ResourceLines = lines
+            .iter()
+            .map(|line| (line.amount, line.resource.clone()))
+            .collect();
+        let reserved: HashMap<String, u64> = lines
+            .iter()
+            .map(|line| (normalize_resource_key(&line.resource), line.reserved))
+            .collect();
+        let when = if hours_left < 0 {
+            format!("was due <t:{}:R>", due.timestamp())
+        } else {
+            format!("is due <t:{}:R>", due.timestamp())
+        };
+        let embed = build_update_embed(&lines[0].product, &resources, &reserved)
+            .description(format!("⏰ **{}** {}.", lines[0].product, when));
+        // A deleted thread or a missing permission mustn't hold up every other reminder
+        let reminder = CreateMessage::new().embed(embed);
+        if let Err(e) = lines[0].thread_id.send_message(http, reminder).await {
+            println!(
+                "❌ Failed to send due reminder for request {}: {:?}",
+                request_id, e
+            );
+            continue;
+        }
+
+        let _guard = ALLOCATION_LOCK.lock().await;
+        let mut lines = store.request_lines(request_id).await?;
+        for line in lines.iter_mut() {
+            line.last_reminder = Some(threshold);
+        }
+        store.update_request_rows(&lines).await?;
+    }
+    Ok(())
+}
+
+// Embed fields hold at most 1024 characters
+pub(crate) fn field_text(lines: &[String], empty: &str) -> String {
+    const MAX: usize = 1000;
+    if lines.is_empty() {
+        return empty.to_string();
+    }
+    let mut text = String::new();
+    for line in lines {
+        if text.len() + line.len() + 1 > MAX {
+            text.push('…');
+            break;
+        }
+        if !text.is_empty() {
+            text.push('\n');
+        }
+        text.push_str(line);
+    }
+    text
+}
+
+/// `(contributor, resource, amount)` each member's submissions handed to `request_id`,
+/// leaving out transactions that were reverted since. Members are told apart by user id
+/// (see `LedgerEntry::contributor`), so a rename doesn't split or merge their totals.
+pub fn contributions(entries: &[LedgerEntry], request_id: &str) -> Vec<(String, String, u64)> {
+    let reverted: Vec<&str> = entries
+        .iter()
+        .filter_map(|entry| entry.reverts.as_deref())
+        .collect();
+
+    let mut totals: Vec<(String, String, u64)> = Vec::new();
+    for entry in entries {
+        if reverted.contains(&entry.txn_id.as_str()) {
+            continue;
+        }
+        for (allocated_to, amount) in &entry.allocations {
+            if allocated_to != request_id {
+                continue;
+            }
+            let resource = normalize_resource_key(&entry.resource);
+            let contributor = entry.contributor();
+            match totals
+                .iter_mut()
+                .find(|(user, res, _)| *user == contributor && *res == resource)
+            {
+                Some((_, _, total)) => *total += amount,
+                None => totals.push((contributor, resource, *amount)),
+            }
+        }
+    }
+    totals
+}
+
+/// Where a finalized request stands: stock reserved for it, what's missing (and how
+/// much of anything that converts into it would cover that), and who promised to bring
+/// what against what they actually submitted
+pub fn request_status_embed(
+    lines: &[RequestRow],
+    entries: &[LedgerEntry],
+    conversions: &Conversions,
+    catalog: &Catalog,
+) -> CreateEmbed {
+    let Some(first) = lines.first() else {
+        return CreateEmbed::new().title("🔷 CRAFTING REQUEST");
+    };
+
+    let mut completed = Vec::new();
+    let mut remaining = Vec::new();
+    let mut commitments = Vec::new();
+    let submitted = contributions(entries, &first.request_id);
+
+    // Only stock reserved for this request counts, not stock another request holds
+    for line in lines {
+        let key = normalize_resource_key(&line.resource);
+        let name = catalog.display_name(&line.resource);
+        if line.reserved >= line.amount {
+            completed.push(format!("• {} x {}", line.amount, name));
+        } else {
+            let missing = line.amount - line.reserved;
+            let alternatives: Vec<String> = conversions
+                .producing(&line.resource)
+                .map(|rule| format!("{} x {}", rule.needed_for(missing), rule.from))
+                .collect();
+            if alternatives.is_empty() {
+                remaining.push(format!("• {} x {}", missing, name));
+            } else {
+                remaining.push(format!(
+                    "• {} x {} (or {})",
+                    missing,
+                    name,
+                    alternatives.join(" or ")
+                ));
+            }
+        }
+
+        // Claims and submissions meet on the mention `contributions` credits
+        let claimants: Vec<(String, u64)> = line
+            .claims
+            .iter()
+            .map(|(user, promised)| (format!("<@{}>", user), *promised))
+            .collect();
+        let submitted_by = |user: &str| {
+            submitted
+                .iter()
+                .find(|(who, res, _)| who == user && *res == key)
+                .map_or(0, |(_, _, amount)| *amount)
+        };
+        let mut people: Vec<String> = claimants
+            .iter()
+            .map(|(user, promised)| format!("{} {}/{}", user, submitted_by(user), promised))
+            .collect();
+        for (user, _, amount) in submitted.iter().filter(|(_, res, _)| *res == key) {
+            if !claimants.iter().any(|(claimant, _)| claimant == user) {
+                people.push(format!("{} {}", user, amount));
+            }
+        }
+        if !people.is_empty() {
+            commitments.push(format!("• **{}**: {}", name, people.join(", ")));
+        }
+    }
+
+    let mut description = Vec::new();
+    if let Some(requester) = first.requester {
+        description.push(format!("Requested by <@{}>", requester));
+    }
+    description.push(match first.crafter {
+        Some(crafter) => format!("Crafter: <@{}>", crafter),
+        None => "Crafter: unassigned (`/request assign`)".to_string(),
+    });
+
+    CreateEmbed::new()
+        .title(format!("🔷 CRAFTING REQUEST: {}", first.product))
+        .description(description.join("\n"))
+        .field(
+            "✅ Reserved:",
+            field_text(&completed, "Nothing yet..."),
+            false,
+        )
+        .field(
+            "🛠 Remaining Materials:",
+            field_text(&remaining, "All materials collected! 🎉"),
+            false,
+        )
+        .field(
+            "🤝 Submitted / promised:",
+            field_text(
+                &commitments,
+                "Nobody has claimed anything yet. Use `/request claim` in this thread.",
+            ),
+            false,
+        )
+}
+
+/// Promise to bring some of a material for the request in this thread (0 takes it back)
+#[poise::command(slash_command)]
+pub async fn claim(
+    ctx: Context<'_>,
+    #[description = "Material you'll bring"]
+    #[autocomplete = "thread_resource_autocomplete"]
+    resource: String,
+    #[description = "How much you'll bring (0 to withdraw your claim)"] amount: u32,
+) -> Result<(), BotError> {
+    let lines = thread_request(ctx).await?;
+
+    let key = normalize_resource_key(&resource);
+    let user = ctx.author().id;
+    let store = ctx.data().store.as_ref();
+    let _guard = ALLOCATION_LOCK.lock().await;
+    let mut line = store
+        .request_lines(&lines[0].request_id)
+        .await?
+        .into_iter()
+        .find(|line| normalize_resource_key(&line.resource) == key)
+        .ok_or(format!("❌ '{}' isn't part of this request.", resource))?;
+    if line.status != RequestStatus::InProgress {
+        return Err("❌ This request is no longer open.".into());
+    }
+    line.claims.retain(|(claimant, _)| *claimant != user);
+    if amount > 0 {
+        line.claims.push((user, amount.into()));
+    }
+    store
+        .update_request_rows(std::slice::from_ref(&line))
+        .await?;
+
+    if amount == 0 {
+        ctx.say(format!("👋 Claim on {} withdrawn.", line.resource))
+            .await?;
+    } else {
+        ctx.say(format!(
+            "🤝 {} will bring {} x {}.",
+            ctx.author().name,
+            amount,
+            line.resource
+        ))
+        .await?;
+    }
+    Ok(())
+}
+
+/// Set who crafts the request in this thread (yourself when no member is given)
+#[poise::command(slash_command)]
+pub async fn assign(
+    ctx: Context<'_>,
+    #[description = "Member who will craft it"] crafter: Option<serenity::User>,
+) -> Result<(), BotError> {
+    let lines = thread_request(ctx).await?;
+    let crafter = crafter.map_or(ctx.author().id, |user| user.id);
+    // Anyone may volunteer for an unassigned request; anything else is the requester's call
+    let volunteering = crafter == ctx.author().id && lines[0].crafter.is_none();
+    if !volunteering {
+        ensure_can_manage(ctx, &lines).await?;
+    }
+
+    let store = ctx.data().store.as_ref();
+    let _guard = ALLOCATION_LOCK.lock().await;
+    let mut lines = store.request_lines(&lines[0].request_id).await?;
+    for line in lines.iter_mut() {
+        line.crafter = Some(crafter);
+    }
+    store.update_request_rows(&lines).await?;
+
+    ctx.say(format!(
+        "🔨 <@{}> is crafting **{}**.",
+        crafter, lines[0].product
+    ))
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(user: &str, user_id: Option<u64>, amount: u64) -> LedgerEntry {
+        LedgerEntry {
+            resource: "Iron Ore".to_string(),
+            amount: amount as i64,
+            date: String::new(),
+            user: user.to_string(),
+            user_id: user_id.map(serenity::UserId::new),
+            reason: None,
+            txn_id: format!("{}-{}", user, amount),
+            reverts: None,
+            allocations: vec![("req".to_string(), amount)],
+        }
+    }
+
+    #[test]
+    fn contributions_follow_the_user_id() {
+        let entries = [
+            entry("paul", Some(1), 100),
+            // Same member after renaming
+            entry("muaddib", Some(1), 50),
+            // Someone else who took the old name
+            entry("paul", Some(2), 30),
+            // Written before ids were recorded
+            entry("paul", None, 5),
+        ];
+        assert_eq!(
+            contributions(&entries, "req"),
+            vec![
+                ("<@1>".to_string(), "iron ore".to_string(), 150),
+                ("<@2>".to_string(), "iron ore".to_string(), 30),
+                ("paul".to_string(), "iron ore".to_string(), 5),
+            ]
+        );
+    }
+}
@@ -1,326 +1,731 @@
-use crate::utils::sheets::{load_inventory_from_sheets, normalize_resource_key};
+use crate::commands::request::field_text;
+use crate::config::SubmitLimits;
+use crate::store::allocation::{ALLOCATION_LOCK, rebalance_store};
+use crate::store::{InventoryAdjustment, LedgerEntry, LedgerStore, RequestRow, RequestStatus};
+use crate::utils::catalog::{Catalog, NameMatch, suggestion_hint, unknown_resource_message};
+use crate::utils::resources::{Conversions, normalize_resource_key, parse_resource_list};
 use crate::{BotError, Context};
 
-use chrono::{DateTime, Utc};
-use dotenvy::dotenv;
-use google_sheets4 as sheets4;
+use poise::serenity_prelude as serenity;
 use poise::serenity_prelude::AutocompleteChoice;
-use poise::serenity_prelude::CreateEmbed;
-use sheets4::{Sheets, api::ValueRange, hyper_rustls, yup_oauth2};
-use std::collections::HashMap;
-use std::env::var;
-
-const ALL_RESOURCES: &[&str] = &[
-    "Advanced Machinery",
-    "Advanced Servoks",
-    "Agave Seeds",
-    "Aluminum Ore",
-    "Armor Plating",
-    "Atmospheric Filtered Fabric",
-    "Ballistic Weave Fabric",
-    "Basalt Stone",
-    "Blade Parts",
-    "Calibrated Servok",
-    "Carbide Blade Parts",
-    "Carbide Scraps",
-    "Carbon Ore",
-    "Complex Machinery",
-    "Copper Ore",
-    "Corpse",
-    "Diamodine Blade Parts",
-    "Diamondine Dust",
-    "EMF Generator",
-    "Erythrite Crystal",
-    "Flour Sand",
-    "Fluid Efficient Industrial Pump",
-    "Fluted Heavy Caliber Compressor",
-    "Fluted Light Caliber Compressor",
-    "Fuel Cell",
-    "Granite Stone",
-    "Gun Parts",
-    "Heavy Caliber Compressor",
-    "Holtzman Actuator",
-    "Hydraulic Piston",
-    "Improved Holtzman Actuator",
-    "Improved Watertube",
-    "Industrial Pump",
-    "Insulated Fabric",
-    "Iron Ore",
-    "Irradiated Core",
-    "Irradiated Slag",
-    "Jasmium Crystal",
-    "Light Caliber Compressor",
-    "Mechanical Parts",
-    "Microsandwich Fabric",
-    "Military Power Regulator",
-    "Mouse Corpse",
-    "Offworld Medical Supplies",
-    "Opafire Gem",
-    "Overclocked Power Regulator",
-    "Particle Capacitor",
-    "Plant Fiber",
-    "Plasteel Composite Armor Plating",
-    "Plasteel Composite Blade Parts",
-    "Plasteel Composite Gun Parts",
-    "Plasteel Microflora Fiber",
-    "Plasteel Plate",
-    "Precision Range Finder",
-    "Range Finder",
-    "Ray Amplifier",
-    "Salvaged Metal",
-    "Sandtrout Leathers",
-    "Ship Manifest",
-    "Solari",
-    "Spice Residue",
-    "Spice Sand",
-    "Spiceinfused Aluminum Dust",
-    "Spiceinfused Copper Dust",
-    "Spiceinfused Duraluminum Dust",
-    "Spiceinfused Iron Dust",
-    "Spiceinfused Plastanium Dust",
-    "Spiceinfused Steel Dust",
-    "Stillsuit Tubing",
-    "Stravidium Mass",
-    "ThermoResponsive Ray Amplifier",
-    "Thermoelectric Cooler",
-    "Titanium Ore",
-    "TriForged Hydraulic Piston",
-];
-
-// Ensure users only pick from a predetermined set of resources
-async fn resource_autocomplete<'a>(_ctx: Context<'a>, partial: &str) -> Vec<AutocompleteChoice> {
-    ALL_RESOURCES
-        .iter()
-        .filter(|name| name.to_lowercase().contains(&partial.to_lowercase()))
+use poise::serenity_prelude::{
+    ButtonStyle, ChannelId, CreateActionRow, CreateButton, CreateEmbed, CreateInteractionResponse,
+    CreateInteractionResponseMessage, CreateMessage, Http,
+};
+use std::time::Duration;
+
+// Ensure users only pick from the resource catalog
+pub(crate) async fn resource_autocomplete<'a>(
+    ctx: Context<'a>,
+    partial: &str,
+) -> Vec<AutocompleteChoice> {
+    ctx.data()
+        .catalog
+        .search(partial)
+        .into_iter()
         .take(25)
-        .map(|name| AutocompleteChoice::new(name.to_string(), name.to_string()))
+        .map(|resource| {
+            let label = match &resource.category {
+                Some(category) => format!("{} ({})", resource.name, category),
+                None => resource.name.clone(),
+            };
+            AutocompleteChoice::new(label, resource.name)
+        })
         .collect()
 }
 
-#[poise::command(slash_command)]
-pub async fn submit(
-    ctx: Context<'_>,
-    #[description = "Resource to submit"]
-    #[autocomplete = "resource_autocomplete"]
-    resource: String,
-    #[description = "Amount to submit"] amount: i32,
-) -> Result<(), BotError> {
-    dotenv().ok();
-    ctx.defer().await?;
-    let service_account_key =
-        yup_oauth2::read_service_account_key("secrets/voltaic-bridge-465115-j2-f15defee98d4.json")
-            .await
-            .expect("Can't read credential, an error occurred");
-
-    let authenticator = yup_oauth2::ServiceAccountAuthenticator::builder(service_account_key)
-        .build()
-        .await
-        .expect("failed to create authenticator");
-
-    let client = hyper_util::client::legacy::Client::builder(hyper_util::rt::TokioExecutor::new())
-        .build(
-            hyper_rustls::HttpsConnectorBuilder::new()
-                .with_native_roots()
-                .unwrap()
-                .https_or_http()
-                .enable_http1()
-                .build(),
-        );
+// Open requests that still need the resource picked in the same command, newest first
+async fn request_autocomplete<'a>(ctx: Context<'a>, partial: &str) -> Vec<AutocompleteChoice> {
+    let resource = match ctx {
+        poise::Context::Application(app) => app
+            .interaction
+            .data
+            .options()
+            .into_iter()
+            .find(|option| option.name == "resource")
+            .and_then(|option| match option.value {
+                poise::serenity_prelude::ResolvedValue::String(value) => Some(value.to_string()),
+                _ => None,
+            }),
+        poise::Context::Prefix(_) => None,
+    };
+    let Ok(rows) = ctx.data().store.request_rows().await else {
+        return Vec::new();
+    };
+
+    let partial = partial.to_lowercase();
+    let mut choices: Vec<AutocompleteChoice> = Vec::new();
+    let mut seen: Vec<&str> = Vec::new();
+    for row in rows.iter().rev() {
+        if row.status != RequestStatus::InProgress
+            || row.reserved >= row.amount
+            || seen.contains(&row.request_id.as_str())
+            || !row.product.to_lowercase().contains(&partial)
+        {
+            continue;
+        }
+        if let Some(resource) = &resource
+            && normalize_resource_key(&row.resource) != normalize_resource_key(resource)
+        {
+            continue;
+        }
+        seen.push(&row.request_id);
+        let label = match &resource {
+            Some(_) => format!(
+                "{} ({} still needed)",
+                row.product,
+                row.amount - row.reserved
+            ),
+            None => row.product.clone(),
+        };
+        choices.push(AutocompleteChoice::new(label, row.request_id.clone()));
+        if choices.len() == 25 {
+            break;
+        }
+    }
+    choices
+}
 
-    let hub = Sheets::new(client, authenticator);
+/// A submission that passed `check_submission`
+pub struct CheckedSubmission {
+    /// Catalog name of the resource to record
+    pub resource: String,
+    pub amount: u32,
+    /// "Converted: …" when what was handed in counts as another resource
+    pub converted: Option<String>,
+}
 
-    let inventory_spreadsheet_id = var("SPREADSHEET_ID_INVENTORY")?;
-    let range = "Sheet1!A:B";
+/// Check a submission against the catalog, the configured conversions and limits,
+/// returning what to record, or why it can't be recorded
+pub fn check_submission(
+    catalog: &Catalog,
+    limits: &SubmitLimits,
+    conversions: &Conversions,
+    resource: &str,
+    amount: i64,
+) -> Result<CheckedSubmission, String> {
+    // What was handed in is recorded as what it counts as
+    let mut converted = None;
+    let (resource, amount) = match conversions.find(resource, catalog) {
+        Some(rule) if amount > 0 => {
+            let to = rule.apply(amount as u64);
+            if to == 0 {
+                return Err(format!(
+                    "❌ {} x {} counts as less than one {}, so there's nothing to submit.",
+                    amount,
+                    resource.trim(),
+                    rule.to
+                ));
+            }
+            converted = Some(format!(
+                "Converted: {} x {} → {} x {}",
+                amount,
+                resource.trim(),
+                to,
+                rule.to
+            ));
+            (rule.to.as_str(), i64::try_from(to).unwrap_or(i64::MAX))
+        }
+        _ => (resource, amount),
+    };
 
-    let ledger_values = hub
-        .spreadsheets()
-        .values_get(&inventory_spreadsheet_id, range)
-        .doit()
-        .await?
-        .1
-        .values
-        .unwrap_or_default();
+    let resource = match catalog.find(resource) {
+        NameMatch::Exact(name) | NameMatch::Corrected(name) => name,
+        NameMatch::Unknown(suggestions) => {
+            return Err(unknown_resource_message(resource, &suggestions));
+        }
+    };
 
-    let mut found_in_ledger = false;
-    let mut updated_ledger_values = vec![];
-    let mut clone_updated_values = updated_ledger_values.clone();
-    let now: DateTime<Utc> = Utc::now();
-    let date = now.to_rfc3339();
-    let user = ctx.author().name.clone();
-    if !ALL_RESOURCES
+    // Corrections (including taking stock back out) go through `/adjust` instead,
+    // so they're officer-only and carry a reason in the ledger
+    if amount <= 0 {
+        return Err("❌ Submissions must be a positive amount. Use `/withdraw` to take resources out, or ask an officer to `/adjust` a mistake.".to_string());
+    }
+    // A single transaction never holds more than a u32
+    let max = limits.max_for(&resource).min(u32::MAX.into());
+    let Some(amount) = u32::try_from(amount)
+        .ok()
+        .filter(|amount| u64::from(*amount) <= max)
+    else {
+        return Err(format!(
+            "❌ {} is too large: submissions of {} are at most {}. If that's really what you brought, ask an officer to `/adjust` the inventory.",
+            amount, resource, max
+        ));
+    };
+    Ok(CheckedSubmission {
+        resource,
+        amount,
+        converted,
+    })
+}
+
+/// The open line of `request_id` that still needs `resource`, or why a submission can't go to it
+pub fn target_line<'a>(
+    rows: &'a [RequestRow],
+    request_id: &str,
+    resource: &str,
+) -> Result<&'a RequestRow, String> {
+    let lines: Vec<&RequestRow> = rows
         .iter()
-        .any(|&r| r.eq_ignore_ascii_case(&resource))
+        .filter(|row| row.request_id == request_id)
+        .collect();
+    let Some(&first) = lines.first() else {
+        return Err(
+            "❌ That request doesn't exist. Pick one from the autocompleted options.".to_string(),
+        );
+    };
+    if first.status != RequestStatus::InProgress {
+        return Err(format!(
+            "❌ The request for **{}** is {}.",
+            first.product,
+            first.status.as_str()
+        ));
+    }
+    let key = normalize_resource_key(resource);
+    match lines
+        .into_iter()
+        .find(|row| normalize_resource_key(&row.resource) == key)
     {
-        ctx.say(format!(
-            "❌ '{}' is not a recognized resource. Please choose from the autocompleted options.",
-            resource
-        ))
-        .await?;
-        return Ok(());
+        Some(line) if line.reserved < line.amount => Ok(line),
+        Some(line) => Err(format!(
+            "❌ **{}** already has all the {} it needs.",
+            line.product, line.resource
+        )),
+        None => Err(format!(
+            "❌ **{}** doesn't need any {}.",
+            first.product, resource
+        )),
+    }
+}
+
+/// Progress of one open request after a submission was allocated to it
+pub struct RequestProgress {
+    pub product: String,
+    pub thread_id: ChannelId,
+    /// How much of this submission went to the request
+    pub allocated: u64,
+    pub completed: Vec<(u64, String)>,
+    pub needed: Vec<(u64, String)>,
+}
+
+/// A recorded submission: its ledger transaction and the requests it moved forward
+pub struct Submission {
+    pub txn_id: String,
+    pub progress: Vec<RequestProgress>,
+}
+
+/// Add `amount` of `resource` to the inventory, reserve the new stock for open requests
+/// that need it and record the transaction (with those reservations) in the ledger.
+/// With a `target` request, that request is served first and the rest goes to the queue.
+pub async fn record_submission(
+    store: &dyn LedgerStore,
+    resource: &str,
+    amount: u32,
+    user: &serenity::User,
+    target: Option<&str>,
+) -> Result<Submission, BotError> {
+    let items = [(amount, resource.to_string())];
+    let mut submissions = record_submissions(store, &items, user, target).await?;
+    Ok(submissions.remove(0))
+}
+
+/// `record_submission` for several resources at once: one inventory update, one ledger
+/// append, one transaction per resource. Resources must not repeat.
+pub async fn record_submissions(
+    store: &dyn LedgerStore,
+    items: &[(u32, String)],
+    user: &serenity::User,
+    target: Option<&str>,
+) -> Result<Vec<Submission>, BotError> {
+    let keys: Vec<String> = items
+        .iter()
+        .map(|(_, resource)| normalize_resource_key(resource))
+        .collect();
+    let deltas: Vec<(String, i64)> = keys
+        .iter()
+        .zip(items)
+        .map(|(key, (amount, _))| (key.clone(), i64::from(*amount)))
+        .collect();
+    let _guard = ALLOCATION_LOCK.lock().await;
+
+    // Only ever adds stock, so it can't come back short
+    let InventoryAdjustment::Applied(_) = store.adjust_inventory(&deltas).await? else {
+        return Err("❌ Couldn't add the submission to the inventory.".into());
+    };
+
+    let mut entries: Vec<LedgerEntry> = items
+        .iter()
+        .map(|(amount, resource)| LedgerEntry::new(resource, (*amount).into(), user, None))
+        .collect();
+    // Each amount is the most a target may take of that resource ahead of the queue
+    let changes = match target {
+        Some(request_id) => {
+            let mut changes = Vec::new();
+            for ((amount, _), key) in items.iter().zip(&keys) {
+                let preferred = Some((request_id, u64::from(*amount)));
+                changes.extend(rebalance_store(store, std::slice::from_ref(key), preferred).await?);
+            }
+            changes
+        }
+        None => rebalance_store(store, &keys, None).await?,
+    };
+    for change in changes.iter().filter(|change| change.delta > 0) {
+        let key = normalize_resource_key(&change.resource);
+        if let Some(pos) = keys.iter().position(|k| *k == key) {
+            entries[pos]
+                .allocations
+                .push((change.request_id.clone(), change.delta as u64));
+        }
     }
+    store.append_ledger(&entries).await?;
 
-    for row in ledger_values {
-        if let Some(name_val) = row.get(0) {
-            if let Some(name) = name_val.as_str() {
-                let resource_key = resource.to_string().to_lowercase();
-                if name.to_lowercase() == resource_key {
-                    let current: i32 = row
-                        .get(1)
-                        .and_then(|v| v.as_str())
-                        .and_then(|s| s.parse::<i32>().ok())
-                        .unwrap_or(0);
-                    let new_value = current + amount;
-                    updated_ledger_values.push(vec![name_val.clone().into(), new_value.into()]);
-                    clone_updated_values.push(vec![
-                        name_val.clone().into(),
-                        amount.into(),
-                        date.clone().into(),
-                        user.clone().into(),
-                    ]);
-                    found_in_ledger = true;
+    // Where each request that got some of it now stands
+    let rows = store.request_rows().await?;
+    let mut submissions = Vec::new();
+    for entry in entries {
+        let mut progress: Vec<RequestProgress> = Vec::new();
+        for (request_id, allocated) in &entry.allocations {
+            let lines: Vec<_> = rows
+                .iter()
+                .filter(|row| row.request_id == *request_id)
+                .collect();
+            let Some(first) = lines.first() else {
+                continue;
+            };
+            let mut request = RequestProgress {
+                product: first.product.clone(),
+                thread_id: first.thread_id,
+                allocated: *allocated,
+                completed: Vec::new(),
+                needed: Vec::new(),
+            };
+            for row in lines {
+                if row.reserved >= row.amount {
+                    request.completed.push((row.amount, row.resource.clone()));
                 } else {
-                    updated_ledger_values.push(row.clone());
+                    request
+                        .needed
+                        .push((row.amount - row.reserved, row.resource.clone()));
                 }
             }
+            progress.push(request);
         }
+        submissions.push(Submission {
+            txn_id: entry.txn_id,
+            progress,
+        });
     }
+    Ok(submissions)
+}
 
-    if !found_in_ledger {
-        updated_ledger_values.push(vec![
-            resource.clone().to_string().to_lowercase().into(),
-            amount.into(),
-        ]);
+/// Credit `user` in the thread of every request their submission went to
+pub async fn announce_submission(http: &Http, user: &str, resource: &str, submission: &Submission) {
+    for request in &submission.progress {
+        let notice = CreateMessage::new().content(format!(
+            "📦 **{}** brought {} x {} for this request (transaction `{}`).",
+            user, request.allocated, resource, submission.txn_id
+        ));
+        if let Err(e) = request.thread_id.send_message(http, notice).await {
+            println!("❌ Error sending to thread: {:?}", e);
+        }
     }
+}
 
-    hub.spreadsheets()
-        .values_update(
-            ValueRange {
-                values: Some(updated_ledger_values),
-                ..Default::default()
-            },
-            &inventory_spreadsheet_id,
-            range,
-        )
-        .value_input_option("RAW")
-        .doit()
-        .await?;
-    //
-    let ledger_spreadsheet_id = var("SPREADSHEET_ID_LEDGER")?;
-    let ledger_range = "Sheet1!A:D";
-    let ledger_values = clone_updated_values;
-
-    hub.spreadsheets()
-        .values_append(
-            ValueRange {
-                values: Some(ledger_values),
-                ..Default::default()
-            },
-            &ledger_spreadsheet_id,
-            ledger_range,
-        )
-        .value_input_option("RAW")
-        .doit()
-        .await?;
+// How long the Contribute form stays open before the click is dropped
+const CONTRIBUTE_TIMEOUT: Duration = Duration::from_secs(10 * 60);
 
-    let request_spreadsheet_id = var("SPREADSHEET_ID_REQUEST")?;
-    let request_range = "Sheet1!A:D";
-    let mut inventory = load_inventory_from_sheets().await?;
-    let request_result = hub
-        .spreadsheets()
-        .values_get(&request_spreadsheet_id, request_range)
-        .doit()
-        .await?;
+#[derive(poise::Modal)]
+#[name = "Contribute to this request"]
+struct ContributeModal {
+    #[name = "Resource"]
+    #[placeholder = "e.g. Iron Ore"]
+    resource: String,
+    #[name = "Amount"]
+    #[placeholder = "e.g. 500"]
+    amount: String,
+}
 
-    let mut request_values = request_result.1.values.unwrap_or_default();
-    let mut updated_request_rows = Vec::new();
-    let mut completed_by_product: HashMap<String, Vec<(u64, String)>> = HashMap::new();
-    let mut needed_by_product: HashMap<String, Vec<(u64, String)>> = HashMap::new();
+// poise only opens modals from something that is `AsRef<serenity::Context>`,
+// which the bare event context isn't
+struct EventContext<'a>(&'a serenity::Context);
 
-    for row in request_values.iter_mut() {
-        if row.len() < 4 {
-            continue;
-        }
+impl AsRef<serenity::Context> for EventContext<'_> {
+    fn as_ref(&self) -> &serenity::Context {
+        self.0
+    }
+}
 
-        let req_resource = normalize_resource_key(row[1].as_str().unwrap_or(""));
-        let req_amount: u64 = row[2].as_str().unwrap_or("0").parse().unwrap_or(0);
-        let status = row[3].as_str().unwrap_or("");
-        let product = row[0].as_str().unwrap_or("").to_string();
-
-        if req_resource == normalize_resource_key(&resource) && status == "in_progress" {
-            let stock = *inventory.get(&req_resource).unwrap_or(&0);
-            if stock >= req_amount {
-                completed_by_product
-                    .entry(product.clone())
-                    .or_default()
-                    .push((req_amount, row[1].to_string().clone()));
-                row[2] = "0".into();
-                inventory.insert(req_resource.clone(), stock - req_amount);
-            } else {
-                let remaining = req_amount - stock;
-                needed_by_product
-                    .entry(product.clone())
-                    .or_default()
-                    .push((remaining, row[1].to_string().clone()));
-                row[2] = remaining.to_string().into();
-                inventory.insert(req_resource.clone(), 0);
-            }
+/// The Contribute button in a request's thread: ask what was brought, then submit it
+/// towards that request the same way `/submit ... request:` does
+pub async fn contribute_to_request(
+    ctx: &serenity::Context,
+    comp: serenity::ComponentInteraction,
+    store: &dyn LedgerStore,
+    catalog: &Catalog,
+    limits: &SubmitLimits,
+    conversions: &Conversions,
+    request_id: &str,
+) -> Result<(), BotError> {
+    let thread_id = comp.channel_id;
+    let author = comp.user.clone();
+    let Some(form) = poise::execute_modal_on_component_interaction::<ContributeModal>(
+        EventContext(ctx),
+        comp,
+        None,
+        Some(CONTRIBUTE_TIMEOUT),
+    )
+    .await?
+    else {
+        return Ok(());
+    };
 
-            updated_request_rows.push(row.clone());
+    let reply =
+        |message: String| CreateMessage::new().content(format!("<@{}> {}", author.id, message));
+    let resource = form.resource.trim();
+    let amount = form.amount.trim().replace([',', '_', ' '], "");
+    let Ok(amount) = amount.parse::<i64>() else {
+        thread_id
+            .send_message(ctx, reply(format!("❌ '{}' isn't an amount.", form.amount)))
+            .await?;
+        return Ok(());
+    };
+    // The form is free text, so this also settles on the catalog's spelling
+    let checked = match check_submission(catalog, limits, conversions, resource, amount) {
+        Ok(checked) => checked,
+        Err(message) => {
+            thread_id.send_message(ctx, reply(message)).await?;
+            return Ok(());
         }
+    };
+    let (resource, amount) = (checked.resource.as_str(), checked.amount);
+    let rows = store.request_rows().await?;
+    if let Err(message) = target_line(&rows, request_id, resource) {
+        thread_id.send_message(ctx, reply(message)).await?;
+        return Ok(());
     }
 
-    if !updated_request_rows.is_empty() {
-        hub.spreadsheets()
-            .values_update(
-                ValueRange {
-                    range: Some(request_range.to_string()),
-                    values: Some(request_values),
-                    ..Default::default()
-                },
-                &request_spreadsheet_id,
-                request_range,
+    let submission = record_submission(store, resource, amount, &author, Some(request_id)).await?;
+    announce_submission(&ctx.http, &author.name, resource, &submission).await;
+    if !submission
+        .progress
+        .iter()
+        .any(|request| request.thread_id == thread_id)
+    {
+        // Everything went to other requests (this one filled up in the meantime)
+        thread_id
+            .send_message(
+                ctx,
+                reply(format!(
+                    "📦 Recorded {} x {} (transaction `{}`); this request didn't need it anymore.{}",
+                    amount,
+                    resource,
+                    submission.txn_id,
+                    checked
+                        .converted
+                        .map(|note| format!("\n{}", note))
+                        .unwrap_or_default()
+                )),
             )
-            .value_input_option("RAW")
-            .doit()
             .await?;
     }
+    Ok(())
+}
 
-    for (product, completed) in completed_by_product.iter() {
-        let empty = Vec::new();
-        let needed = needed_by_product.get(product).unwrap_or(&empty);
+fn format_lines(lines: &[(u64, String)]) -> String {
+    lines
+        .iter()
+        .map(|(amt, res)| format!("• {} x {}", amt, res))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
 
-        let comp_text = if completed.is_empty() {
+#[poise::command(slash_command)]
+pub async fn submit(
+    ctx: Context<'_>,
+    #[description = "Resource to submit"]
+    #[autocomplete = "resource_autocomplete"]
+    resource: String,
+    #[description = "Amount to submit"] amount: i32,
+    #[description = "Request to put it towards first (defaults to the queue)"]
+    #[autocomplete = "request_autocomplete"]
+    request: Option<String>,
+) -> Result<(), BotError> {
+    ctx.defer().await?;
+    let config = &ctx.data().config;
+    let (limits, conversions) = (&config.submit_limits, &config.conversions);
+    let checked = match check_submission(
+        &ctx.data().catalog,
+        limits,
+        conversions,
+        &resource,
+        amount.into(),
+    ) {
+        Ok(checked) => checked,
+        Err(message) => {
+            ctx.say(message).await?;
+            return Ok(());
+        }
+    };
+    let (resource, amount) = (checked.resource, checked.amount);
+
+    let store = ctx.data().store.as_ref();
+    if let Some(request_id) = &request {
+        let rows = store.request_rows().await?;
+        if let Err(message) = target_line(&rows, request_id, &resource) {
+            ctx.say(message).await?;
+            return Ok(());
+        }
+    }
+
+    let user = ctx.author().name.clone();
+    let submission =
+        record_submission(store, &resource, amount, ctx.author(), request.as_deref()).await?;
+
+    for request in &submission.progress {
+        let comp_text = if request.completed.is_empty() {
             "Nothing completed yet.".to_string()
         } else {
-            completed
-                .iter()
-                .map(|(amt, res)| format!("• {} x {}", amt, res))
-                .collect::<Vec<_>>()
-                .join("\n")
+            format_lines(&request.completed)
         };
 
-        let rem_text = if needed.is_empty() {
+        let rem_text = if request.needed.is_empty() {
             "✅ All materials fulfilled!".to_string()
         } else {
-            needed
-                .iter()
-                .map(|(amt, res)| format!("• {} x {}", amt, res))
-                .collect::<Vec<_>>()
-                .join("\n")
+            format_lines(&request.needed)
         };
 
         let embed = CreateEmbed::new()
-            .title(format!("📦 Updated Request: {}", product))
+            .title(format!("📦 Updated Request: {}", request.product))
             .field("✅ Completed:", comp_text, false)
             .field("🛠️ Still Needed:", rem_text, false);
 
         ctx.send(poise::CreateReply::default().embed(embed)).await?;
     }
+    // Credit the contributor where each request's crew can see it
+    announce_submission(ctx.http(), &user, &resource, &submission).await;
 
+    let converted = checked
+        .converted
+        .map(|note| format!("\n{}", note))
+        .unwrap_or_default();
     ctx.say(format!(
-        "✅ Submitted {} of {} to the sheet!",
-        amount, resource
+        "✅ Submitted {} of {} to the sheet! (transaction `{}`){}",
+        amount, resource, submission.txn_id, converted
     ))
     .await?;
     Ok(())
 }
+
+// How long the bulk preview waits for Confirm before nothing is recorded
+const BULK_CONFIRM_TIMEOUT: Duration = Duration::from_secs(2 * 60);
+
+// Discord won't let `/submit` have its own options and a `bulk` subcommand at the same
+// time, so pasted lists get their own top-level command
+/// Submit every resource in a pasted list at once, after a preview
+#[poise::command(slash_command)]
+pub async fn submit_bulk(
+    ctx: Context<'_>,
+    #[description = "Paste the raw resource list here"] raw_resource_list: String,
+) -> Result<(), BotError> {
+    ctx.defer().await?;
+    let config = &ctx.data().config;
+    let (limits, conversions) = (&config.submit_limits, &config.conversions);
+    let catalog = &ctx.data().catalog;
+
+    // Known resources merged by name; anything else is shown but not submitted
+    let mut accepted: Vec<(u64, String)> = Vec::new();
+    let mut skipped: Vec<String> = Vec::new();
+    let mut corrected: Vec<String> = Vec::new();
+    for item in parse_resource_list(&raw_resource_list, conversions, catalog) {
+        if item.amount == 0 {
+            skipped.push(format!("• {} — nothing to submit", item.describe()));
+            continue;
+        }
+        let name = match catalog.find(&item.name) {
+            NameMatch::Exact(name) => {
+                if item.converted_from.is_some() {
+                    corrected.push(format!("• {}", item.describe()));
+                }
+                name
+            }
+            NameMatch::Corrected(name) => {
+                corrected.push(format!("• {} → {}", item.name, name));
+                name
+            }
+            NameMatch::Unknown(suggestions) => {
+                skipped.push(format!(
+                    "• {} — {}",
+                    item.describe(),
+                    suggestion_hint(&suggestions)
+                ));
+                continue;
+            }
+        };
+        match accepted.iter_mut().find(|(_, existing)| *existing == name) {
+            Some((total, _)) => *total += item.amount,
+            None => accepted.push((item.amount, name)),
+        }
+    }
+    let mut items: Vec<(u32, String)> = Vec::new();
+    for (amount, name) in accepted {
+        let amount = i64::try_from(amount).unwrap_or(i64::MAX);
+        match check_submission(catalog, limits, conversions, &name, amount) {
+            Ok(checked) => items.push((checked.amount, checked.resource)),
+            Err(message) => skipped.push(format!(
+                "• {} x {} — {}",
+                amount,
+                name,
+                message.trim_start_matches("❌ ")
+            )),
+        }
+    }
+    if items.is_empty() {
+        let embed = CreateEmbed::new()
+            .title("❌ Nothing in that list can be submitted")
+            .field(
+                "⚠️ Skipped",
+                field_text(&skipped, "No resources found."),
+                false,
+            );
+        ctx.send(poise::CreateReply::default().embed(embed)).await?;
+        return Ok(());
+    }
+
+    let listed: Vec<String> = items
+        .iter()
+        .map(|(amount, name)| format!("• {} x {}", amount, name))
+        .collect();
+    let mut preview = CreateEmbed::new().title("📋 Bulk submission").field(
+        "📦 To submit",
+        field_text(&listed, ""),
+        false,
+    );
+    if !corrected.is_empty() {
+        preview = preview.field("✏️ Read as", field_text(&corrected, ""), false);
+    }
+    if !skipped.is_empty() {
+        preview = preview.field("⚠️ Skipped", field_text(&skipped, ""), false);
+    }
+    let buttons = vec![CreateActionRow::Buttons(vec![
+        CreateButton::new("submit_bulk_confirm")
+            .label("Submit")
+            .style(ButtonStyle::Success),
+        CreateButton::new("submit_bulk_cancel")
+            .label("Cancel")
+            .style(ButtonStyle::Danger),
+    ])];
+    let reply = ctx
+        .send(
+            poise::CreateReply::default()
+                .embed(preview.clone())
+                .components(buttons),
+        )
+        .await?;
+
+    let message = reply.message().await?;
+    let pressed = message
+        .await_component_interaction(ctx.serenity_context())
+        .author_id(ctx.author().id)
+        .timeout(BULK_CONFIRM_TIMEOUT)
+        .await;
+    let Some(pressed) = pressed else {
+        reply
+            .edit(
+                ctx,
+                poise::CreateReply::default()
+                    .content("⌛ Nothing was submitted; the preview timed out.")
+                    .embed(preview)
+                    .components(Vec::new()),
+            )
+            .await?;
+        return Ok(());
+    };
+    let confirmed = pressed.data.custom_id == "submit_bulk_confirm";
+    let status = if confirmed {
+        "⏳ Submitting…"
+    } else {
+        "🗑️ Bulk submission cancelled; nothing was recorded."
+    };
+    pressed
+        .create_response(
+            ctx.http(),
+            CreateInteractionResponse::UpdateMessage(
+                CreateInteractionResponseMessage::new()
+                    .content(status)
+                    .components(Vec::new()),
+            ),
+        )
+        .await?;
+    if !confirmed {
+        return Ok(());
+    }
+
+    let user = ctx.author().name.clone();
+    let submissions =
+        record_submissions(ctx.data().store.as_ref(), &items, ctx.author(), None).await?;
+    let mut lines = Vec::new();
+    for ((amount, resource), submission) in items.iter().zip(&submissions) {
+        announce_submission(ctx.http(), &user, resource, submission).await;
+        let products: Vec<&str> = submission
+            .progress
+            .iter()
+            .map(|request| request.product.as_str())
+            .collect();
+        let mut line = format!("• {} x {} (`{}`)", amount, resource, submission.txn_id);
+        if !products.is_empty() {
+            line.push_str(&format!(" → {}", products.join(", ")));
+        }
+        lines.push(line);
+    }
+    let embed = CreateEmbed::new()
+        .title(format!("✅ Submitted {} resources", items.len()))
+        .field("📦 Recorded", field_text(&lines, ""), false);
+    ctx.send(poise::CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn check(amount: i64) -> Result<CheckedSubmission, String> {
+        // A fresh copy of the bundled catalog
+        let path = std::env::temp_dir().join(format!("catalog-{}.toml", uuid::Uuid::new_v4()));
+        let catalog = Catalog::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let limits = SubmitLimits {
+            default_max: 5000,
+            per_resource: HashMap::new(),
+        };
+        check_submission(
+            &catalog,
+            &limits,
+            &Conversions::new(Vec::new()),
+            "Iron Ore",
+            amount,
+        )
+    }
+
+    #[test]
+    fn amounts_must_be_positive() {
+        for amount in [0, -5] {
+            let error = check(amount).err().unwrap();
+            assert!(error.contains("must be a positive amount"), "{}", error);
+        }
+    }
+
+    #[test]
+    fn amounts_over_the_limit_are_too_large() {
+        assert_eq!(check(5000).unwrap().amount, 5000);
+        for amount in [5001, i64::from(u32::MAX) + 1, i64::MAX] {
+            let error = check(amount).err().unwrap();
+            assert!(
+                error.starts_with(&format!("❌ {} is too large", amount))
+                    && error.contains("at most 5000"),
+                "{}",
+                error
+            );
+        }
+    }
+}
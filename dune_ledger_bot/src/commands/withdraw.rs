@@ -0,0 +1,80 @@
+use crate::commands::adjust::{AdjustOutcome, record_adjustment, released_note};
+use crate::commands::submit::resource_autocomplete;
+use crate::utils::catalog::{NameMatch, unknown_resource_message};
+use crate::utils::checks::author_is_officer;
+use crate::{BotError, Context};
+
+/// Take resources out of the guild inventory, e.g. for personal crafting
+#[poise::command(slash_command)]
+pub async fn withdraw(
+    ctx: Context<'_>,
+    #[description = "Resource to withdraw"]
+    #[autocomplete = "resource_autocomplete"]
+    resource: String,
+    #[description = "Amount to withdraw"]
+    #[min = 1]
+    amount: u32,
+    #[description = "What it's for"] reason: Option<String>,
+) -> Result<(), BotError> {
+    ctx.defer().await?;
+    let resource = match ctx.data().catalog.find(&resource) {
+        NameMatch::Exact(name) | NameMatch::Corrected(name) => name,
+        NameMatch::Unknown(suggestions) => {
+            ctx.say(unknown_resource_message(&resource, &suggestions))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    // Withdrawals go in the ledger as negative transactions. Only officers may dip into
+    // stock set aside for open requests.
+    let keep_reserved = !author_is_officer(ctx).await;
+    let outcome = record_adjustment(
+        ctx.data().store.as_ref(),
+        &resource,
+        -i64::from(amount),
+        ctx.author(),
+        reason,
+        keep_reserved,
+    )
+    .await?;
+
+    match outcome {
+        AdjustOutcome::Applied {
+            left,
+            txn_id,
+            released,
+        } => {
+            ctx.say(format!(
+                "✅ Withdrew {} of {} from the inventory ({} left, transaction `{}`).{}",
+                amount,
+                resource,
+                left,
+                txn_id,
+                released_note(&released)
+            ))
+            .await?;
+        }
+        AdjustOutcome::Reserved {
+            available,
+            products,
+        } => {
+            ctx.say(format!(
+                "❌ Can't withdraw {} of {}: only {} isn't reserved for open requests ({}). Ask an officer if those requests can spare it.",
+                amount,
+                resource,
+                available,
+                products.join(", ")
+            ))
+            .await?;
+        }
+        AdjustOutcome::Insufficient { stock } => {
+            ctx.say(format!(
+                "❌ Can't withdraw {} of {}: only {} in the inventory.",
+                amount, resource, stock
+            ))
+            .await?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,537 @@
+use crate::utils::resources::{Conversion, Conversions, Rounding, normalize_resource_key};
+use poise::serenity_prelude::{ChannelId, GuildId, RoleId};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::env::var;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+const DEFAULT_CONFIG_PATH: &str = "config.toml";
+const DEFAULT_SUBMIT_MAX: u64 = 1_000_000;
+const DEFAULT_DRAFT_IDLE_MINUTES: u64 = 12 * 60;
+const DEFAULT_REMINDER_HOURS: &[u64] = &[48, 12];
+
+/// Everything the bot needs to run, loaded once at startup from the environment
+/// (`.env` included) layered over an optional TOML file. Environment variables win.
+pub struct Config {
+    pub discord_token: String,
+    pub guild_id: GuildId,
+    /// The channel in which `/request finish` posts its threads
+    pub requests_channel_id: ChannelId,
+    /// Members with this role (or Manage Server) may run officer-only commands
+    pub officer_role_id: Option<RoleId>,
+    pub submit_limits: SubmitLimits,
+    /// Resources that count as another when listed or submitted
+    pub conversions: Conversions,
+    pub store: StoreConfig,
+    /// Where unfinished `/request` drafts are kept between restarts
+    pub drafts_path: PathBuf,
+    /// Drafts untouched for this long are dropped and their author told
+    pub draft_idle_minutes: u64,
+    /// Recipe catalog for `/request start`; the bundled one when unset
+    pub recipes_path: Option<PathBuf>,
+    /// Resource catalog maintained by `/catalog`, created from the bundled one if missing
+    pub catalog_path: PathBuf,
+    /// How many hours before a request's due date to remind its thread, largest first
+    pub reminder_hours: Vec<u64>,
+}
+
+/// Upper bound on a single `/submit`, to catch a slipped zero before it hits the ledger
+pub struct SubmitLimits {
+    pub default_max: u64,
+    /// Overrides keyed by normalized resource name
+    pub per_resource: HashMap<String, u64>,
+}
+
+impl SubmitLimits {
+    pub fn max_for(&self, resource: &str) -> u64 {
+        self.per_resource
+            .get(&normalize_resource_key(resource))
+            .copied()
+            .unwrap_or(self.default_max)
+    }
+}
+
+pub enum StoreConfig {
+    Sheets(SheetsConfig),
+    Sqlite { path: PathBuf },
+    Memory,
+}
+
+pub struct SheetsConfig {
+    pub service_account_path: PathBuf,
+    pub inventory: SheetLocation,
+    pub ledger: SheetLocation,
+    pub request: SheetLocation,
+}
+
+/// A spreadsheet and the tab in it the bot reads and writes
+pub struct SheetLocation {
+    pub spreadsheet_id: String,
+    pub tab: String,
+}
+
+impl SheetLocation {
+    /// A1 range over `columns` (e.g. `"A:B"`) of this tab
+    pub fn range(&self, columns: &str) -> String {
+        format!("{}!{}", self.tab, columns)
+    }
+}
+
+/// Every problem found while loading the configuration, reported together
+#[derive(Debug)]
+pub struct ConfigError {
+    pub problems: Vec<String>,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Invalid configuration:")?;
+        for problem in &self.problems {
+            writeln!(f, "  - {}", problem)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+// Shape of the optional TOML file; every key can also come from the environment
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct FileConfig {
+    discord_token: Option<String>,
+    guild_id: Option<u64>,
+    requests_channel_id: Option<u64>,
+    officer_role_id: Option<u64>,
+    store: Option<String>,
+    sqlite_path: Option<PathBuf>,
+    drafts_path: Option<PathBuf>,
+    draft_idle_minutes: Option<u64>,
+    recipes_path: Option<PathBuf>,
+    catalog_path: Option<PathBuf>,
+    reminder_hours: Option<Vec<u64>>,
+    submit_limits: FileSubmitLimits,
+    conversions: Option<Vec<FileConversion>>,
+    sheets: FileSheets,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct FileSubmitLimits {
+    default: Option<u64>,
+    resources: HashMap<String, u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FileConversion {
+    from: String,
+    to: String,
+    #[serde(default = "one")]
+    amount: u64,
+    counts_as: u64,
+    #[serde(default)]
+    rounding: Rounding,
+}
+
+fn one() -> u64 {
+    1
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct FileSheets {
+    service_account_path: Option<PathBuf>,
+    inventory: FileSheet,
+    ledger: FileSheet,
+    request: FileSheet,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct FileSheet {
+    spreadsheet_id: Option<String>,
+    tab: Option<String>,
+}
+
+impl Config {
+    /// Read `LEDGER_CONFIG` (or `config.toml` when present) and the environment,
+    /// validating every value before the bot connects to anything
+    pub fn load() -> Result<Config, ConfigError> {
+        let mut problems = Vec::new();
+
+        let file = match var("LEDGER_CONFIG") {
+            Ok(path) => read_file(Path::new(&path), &mut problems),
+            Err(_) if Path::new(DEFAULT_CONFIG_PATH).exists() => {
+                read_file(Path::new(DEFAULT_CONFIG_PATH), &mut problems)
+            }
+            Err(_) => FileConfig::default(),
+        };
+
+        let discord_token = required("DISCORD_TOKEN", file.discord_token, &mut problems);
+        let guild_id = snowflake("GUILD_ID", file.guild_id, &mut problems).map(GuildId::new);
+        let requests_channel_id = snowflake(
+            "REQUESTS_CHANNEL_ID",
+            file.requests_channel_id,
+            &mut problems,
+        )
+        .map(ChannelId::new);
+        // Optional: without it, officer commands fall back to the Manage Server permission
+        let officer_role_id = if var("OFFICER_ROLE_ID").is_ok() || file.officer_role_id.is_some() {
+            snowflake("OFFICER_ROLE_ID", file.officer_role_id, &mut problems).map(RoleId::new)
+        } else {
+            None
+        };
+        let submit_limits = submit_limits(file.submit_limits, &mut problems);
+        let conversions = conversions(file.conversions, &mut problems);
+        let recipes_path = var("RECIPES_PATH")
+            .ok()
+            .map(PathBuf::from)
+            .or(file.recipes_path);
+        let catalog_path = var("CATALOG_PATH")
+            .ok()
+            .map(PathBuf::from)
+            .or(file.catalog_path)
+            .unwrap_or_else(|| PathBuf::from("catalog.toml"));
+        let mut reminder_hours = match var("REMINDER_HOURS") {
+            Ok(raw) => raw
+                .split(',')
+                .filter(|hours| !hours.trim().is_empty())
+                .filter_map(|hours| match hours.trim().parse::<u64>() {
+                    Ok(hours) => Some(hours),
+                    Err(_) => {
+                        problems.push(format!(
+                            "REMINDER_HOURS must be comma-separated whole numbers (got '{}')",
+                            raw
+                        ));
+                        None
+                    }
+                })
+                .collect(),
+            Err(_) => file
+                .reminder_hours
+                .unwrap_or_else(|| DEFAULT_REMINDER_HOURS.to_vec()),
+        };
+        reminder_hours.sort_unstable_by(|a, b| b.cmp(a));
+        reminder_hours.dedup();
+        let drafts_path = var("DRAFTS_PATH")
+            .ok()
+            .map(PathBuf::from)
+            .or(file.drafts_path)
+            .unwrap_or_else(|| PathBuf::from("drafts.json"));
+        let draft_idle_minutes = match var("DRAFT_IDLE_MINUTES") {
+            Ok(raw) => match raw.trim().parse::<u64>() {
+                Ok(minutes) if minutes > 0 => minutes,
+                _ => {
+                    problems.push(format!(
+                        "DRAFT_IDLE_MINUTES must be a positive whole number (got '{}')",
+                        raw
+                    ));
+                    DEFAULT_DRAFT_IDLE_MINUTES
+                }
+            },
+            Err(_) => match file.draft_idle_minutes {
+                Some(0) => {
+                    problems.push("draft_idle_minutes must be above zero".to_string());
+                    DEFAULT_DRAFT_IDLE_MINUTES
+                }
+                Some(minutes) => minutes,
+                None => DEFAULT_DRAFT_IDLE_MINUTES,
+            },
+        };
+
+        let backend = var("LEDGER_STORE")
+            .ok()
+            .or(file.store)
+            .unwrap_or_else(|| "sheets".to_string());
+        let store = match backend.trim().to_lowercase().as_str() {
+            "sheets" => Some(StoreConfig::Sheets(sheets_config(
+                file.sheets,
+                &mut problems,
+            ))),
+            "sqlite" => Some(StoreConfig::Sqlite {
+                path: var("SQLITE_PATH")
+                    .ok()
+                    .map(PathBuf::from)
+                    .or(file.sqlite_path)
+                    .unwrap_or_else(|| PathBuf::from("ledger.db")),
+            }),
+            "memory" => Some(StoreConfig::Memory),
+            other => {
+                problems.push(format!(
+                    "LEDGER_STORE must be one of sheets, sqlite or memory (got '{}')",
+                    other
+                ));
+                None
+            }
+        };
+
+        match (discord_token, guild_id, requests_channel_id, store) {
+            (Some(discord_token), Some(guild_id), Some(requests_channel_id), Some(store))
+                if problems.is_empty() =>
+            {
+                Ok(Config {
+                    discord_token,
+                    guild_id,
+                    requests_channel_id,
+                    officer_role_id,
+                    submit_limits,
+                    conversions,
+                    store,
+                    drafts_path,
+                    draft_idle_minutes,
+                    recipes_path,
+                    catalog_path,
+                    reminder_hours,
+                })
+            }
+            _ => Err(ConfigError { problems }),
+        }
+    }
+}
+
+fn read_file(path: &Path, problems: &mut Vec<String>) -> FileConfig {
+    let parsed = std::fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|raw| toml::from_str(&raw).map_err(|e| e.to_string()));
+    parsed.unwrap_or_else(|e| {
+        problems.push(format!("Can't load config file {}: {}", path.display(), e));
+        FileConfig::default()
+    })
+}
+
+fn required(key: &str, fallback: Option<String>, problems: &mut Vec<String>) -> Option<String> {
+    let value = var(key).ok().or(fallback).filter(|v| !v.trim().is_empty());
+    if value.is_none() {
+        problems.push(format!("{} is not set", key));
+    }
+    value
+}
+
+fn snowflake(key: &str, fallback: Option<u64>, problems: &mut Vec<String>) -> Option<u64> {
+    let raw = match var(key) {
+        Ok(raw) => raw,
+        Err(_) => match fallback {
+            Some(id) => id.to_string(),
+            None => {
+                problems.push(format!("{} is not set", key));
+                return None;
+            }
+        },
+    };
+    match raw.trim().parse::<u64>() {
+        Ok(id) if id > 0 => Some(id),
+        _ => {
+            problems.push(format!("{} must be a Discord ID (got '{}')", key, raw));
+            None
+        }
+    }
+}
+
+fn submit_limits(file: FileSubmitLimits, problems: &mut Vec<String>) -> SubmitLimits {
+    let default_max = match var("SUBMIT_MAX_AMOUNT") {
+        Ok(raw) => raw.trim().parse::<u64>().unwrap_or_else(|_| {
+            problems.push(format!(
+                "SUBMIT_MAX_AMOUNT must be a whole number (got '{}')",
+                raw
+            ));
+            DEFAULT_SUBMIT_MAX
+        }),
+        Err(_) => file.default.unwrap_or(DEFAULT_SUBMIT_MAX),
+    };
+
+    SubmitLimits {
+        default_max,
+        per_resource: file
+            .resources
+            .into_iter()
+            .map(|(name, max)| (normalize_resource_key(&name), max))
+            .collect(),
+    }
+}
+
+// Without a `conversions` table, water is handed in as corpses, 45,000 to one
+fn conversions(file: Option<Vec<FileConversion>>, problems: &mut Vec<String>) -> Conversions {
+    let Some(file) = file else {
+        return Conversions::new(vec![Conversion {
+            from: "Water".to_string(),
+            to: "Corpse".to_string(),
+            amount: 45_000,
+            counts_as: 1,
+            rounding: Rounding::Down,
+        }]);
+    };
+
+    let froms: Vec<String> = file
+        .iter()
+        .map(|c| normalize_resource_key(&c.from))
+        .collect();
+    let mut rules: Vec<Conversion> = Vec::new();
+    for (idx, c) in file.into_iter().enumerate() {
+        let (from, to) = (
+            normalize_resource_key(&c.from),
+            normalize_resource_key(&c.to),
+        );
+        let label = format!("conversion {} → {}", c.from, c.to);
+        if from.is_empty() || to.is_empty() {
+            problems.push(format!("{} needs both resources named", label));
+        } else if c.amount == 0 || c.counts_as == 0 {
+            problems.push(format!("{} needs amount and counts_as above zero", label));
+        } else if from == to {
+            problems.push(format!("{} converts a resource into itself", label));
+        } else if froms[..idx].contains(&from) {
+            problems.push(format!("{} is the second conversion for {}", label, c.from));
+        } else if froms.contains(&to) {
+            // Applied once, so a chain would stop halfway
+            problems.push(format!("{} leads into another conversion", label));
+        } else {
+            rules.push(Conversion {
+                from: c.from.trim().to_string(),
+                to: c.to.trim().to_string(),
+                amount: c.amount,
+                counts_as: c.counts_as,
+                rounding: c.rounding,
+            });
+        }
+    }
+    Conversions::new(rules)
+}
+
+fn sheets_config(file: FileSheets, problems: &mut Vec<String>) -> SheetsConfig {
+    let service_account_path = var("GOOGLE_SVC_ACCOUNT_KEY")
+        .ok()
+        .map(PathBuf::from)
+        .or(file.service_account_path)
+        .unwrap_or_default();
+    if service_account_path.as_os_str().is_empty() {
+        problems.push("GOOGLE_SVC_ACCOUNT_KEY is not set".to_string());
+    } else if !service_account_path.is_file() {
+        problems.push(format!(
+            "GOOGLE_SVC_ACCOUNT_KEY points to {}, which doesn't exist",
+            service_account_path.display()
+        ));
+    }
+
+    let mut location = |key: &str, file: FileSheet| SheetLocation {
+        spreadsheet_id: required(key, file.spreadsheet_id, problems).unwrap_or_default(),
+        tab: file.tab.unwrap_or_else(|| "Sheet1".to_string()),
+    };
+
+    SheetsConfig {
+        service_account_path,
+        inventory: location("SPREADSHEET_ID_INVENTORY", file.inventory),
+        ledger: location("SPREADSHEET_ID_LEDGER", file.ledger),
+        request: location("SPREADSHEET_ID_REQUEST", file.request),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // `(from, to, amount, counts_as, rounding)` of every rule that loaded
+    type Rule = (String, String, u64, u64, Rounding);
+
+    fn load_conversions(raw: &str) -> (Vec<Rule>, Vec<String>) {
+        let file: FileConfig = toml::from_str(raw).unwrap();
+        let mut problems = Vec::new();
+        let rules = conversions(file.conversions, &mut problems)
+            .iter()
+            .map(|c| {
+                (
+                    c.from.clone(),
+                    c.to.clone(),
+                    c.amount,
+                    c.counts_as,
+                    c.rounding,
+                )
+            })
+            .collect();
+        (rules, problems)
+    }
+
+    fn rule(from: &str, to: &str, amount: u64, counts_as: u64, rounding: Rounding) -> Rule {
+        (
+            from.to_string(),
+            to.to_string(),
+            amount,
+            counts_as,
+            rounding,
+        )
+    }
+
+    #[test]
+    fn water_to_corpse_without_a_table() {
+        let (rules, problems) = load_conversions("");
+        assert!(problems.is_empty());
+        assert_eq!(
+            rules,
+            vec![rule("Water", "Corpse", 45_000, 1, Rounding::Down)]
+        );
+    }
+
+    #[test]
+    fn empty_table_turns_conversions_off() {
+        let (rules, problems) = load_conversions("conversions = []");
+        assert!(problems.is_empty());
+        assert!(rules.is_empty());
+    }
+
+    #[test]
+    fn amount_and_rounding_default() {
+        let (rules, problems) = load_conversions(
+            "[[conversions]]\nfrom = \"Spice Sand\"\nto = \"Spice Residue\"\ncounts_as = 2\n",
+        );
+        assert!(problems.is_empty());
+        assert_eq!(
+            rules,
+            vec![rule("Spice Sand", "Spice Residue", 1, 2, Rounding::Down)]
+        );
+    }
+
+    #[test]
+    fn bad_conversions_are_reported() {
+        let (rules, problems) = load_conversions(
+            r#"
+            [[conversions]]
+            from = "Spice Sand"
+            to = "Spice Residue"
+            amount = 0
+            counts_as = 1
+
+            [[conversions]]
+            from = "Water"
+            to = "water"
+            counts_as = 1
+
+            [[conversions]]
+            from = "Corpse"
+            to = "Spice Sand"
+            counts_as = 1
+
+            [[conversions]]
+            from = "corpse"
+            to = "Iron Ore"
+            counts_as = 1
+            "#,
+        );
+        assert!(rules.is_empty());
+        assert_eq!(
+            problems,
+            vec![
+                "conversion Spice Sand → Spice Residue needs amount and counts_as above zero",
+                "conversion Water → water converts a resource into itself",
+                "conversion Corpse → Spice Sand leads into another conversion",
+                "conversion corpse → Iron Ore is the second conversion for corpse",
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_rounding_is_rejected() {
+        let raw =
+            "[[conversions]]\nfrom = \"a\"\nto = \"b\"\ncounts_as = 1\nrounding = \"sideways\"\n";
+        assert!(toml::from_str::<FileConfig>(raw).is_err());
+    }
+}
@@ -1,29 +1,153 @@
 mod commands;
+mod config;
+mod store;
 mod utils;
 
-use utils::sheets::{complete_request, load_inventory_from_sheets, load_request_from_sheets};
-
-use commands::request::request;
-use commands::submit::submit;
+use commands::adjust::adjust;
+use commands::catalog::stock_under_aliases;
+use commands::ledger::ledger;
+use commands::request::{complete_request, request, request_status_embed, send_due_reminders};
+use commands::submit::{contribute_to_request, submit, submit_bulk};
+use commands::withdraw::withdraw;
+use config::Config;
 use dotenvy::dotenv;
 use poise::builtins::register_in_guild;
 use poise::serenity_prelude as serenity;
-use serenity::{CreateEmbed, CreateMessage, GuildId};
-use std::env::var;
+use serenity::CreateMessage;
+use std::sync::Arc;
+use std::time::Duration;
+use store::LedgerStore;
+use utils::catalog::Catalog;
+use utils::drafts;
+use utils::recipes::RecipeBook;
 
 type BotError = Box<dyn std::error::Error + Send + Sync>;
 type Context<'a> = poise::Context<'a, Data, BotError>;
-struct Data {}
+struct Data {
+    config: Config,
+    store: Arc<dyn LedgerStore>,
+    recipes: RecipeBook,
+    catalog: Catalog,
+}
+
+// How often idle drafts are looked for
+const DRAFT_SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+// How often due dates are checked for reminders
+const REMINDER_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+// Load the configuration, recipes and catalog, open the store (for Sheets, one
+// authenticated hub shared by every command) and restore saved drafts. Done before
+// connecting so any problem stops the bot right here.
+async fn startup() -> Result<(Config, Arc<dyn LedgerStore>, RecipeBook, Catalog), BotError> {
+    let config = Config::load()?;
+    let recipes = RecipeBook::load(config.recipes_path.as_deref())?;
+    let catalog = Catalog::load(&config.catalog_path)?;
+    // A conversion into an unknown resource would leave every converted line flagged
+    for conversion in config.conversions.iter() {
+        if catalog.resolve(&conversion.to).is_none() {
+            return Err(format!(
+                "Conversion {} → {}: '{}' is not in the catalog",
+                conversion.from, conversion.to, conversion.to
+            )
+            .into());
+        }
+    }
+    // Likewise a recipe naming an unknown resource would fill requests with lines
+    // that never match any stock
+    for recipe in recipes.iter() {
+        for name in std::iter::once(&recipe.product).chain(recipe.ingredients.keys()) {
+            if catalog.resolve(name).is_none() {
+                return Err(format!(
+                    "Recipe for {}: '{}' is not in the catalog",
+                    recipe.product, name
+                )
+                .into());
+            }
+        }
+    }
+    let store = store::open(&config.store).await?;
+    // Moving stock is left to officers (`/catalog reload`), so nothing changes unseen
+    let (inventory, rows) = (store.inventory().await?, store.request_rows().await?);
+    for stock in stock_under_aliases(&inventory, &rows, &catalog) {
+        println!(
+            "⚠️ {} x {} is kept under a name the catalog now treats as an alias of {}. \
+            Run `/catalog reload` to move it there.",
+            stock.amount, stock.alias, stock.name
+        );
+    }
+    let restored = drafts::load(&config.drafts_path)?;
+    if restored > 0 {
+        println!("Restored {} unfinished request draft(s)", restored);
+    }
+    Ok((config, store, recipes, catalog))
+}
+
+// Drop drafts nobody has touched in a while, telling each author where they started it
+async fn expire_drafts(http: Arc<serenity::Http>, idle_minutes: u64) {
+    let idle = chrono::Duration::minutes(idle_minutes as i64);
+    let mut interval = tokio::time::interval(DRAFT_SWEEP_INTERVAL);
+    loop {
+        interval.tick().await;
+        let expired = match drafts::take_expired(idle) {
+            Ok(expired) => expired,
+            Err(e) => {
+                println!("❌ Failed to expire drafts: {}", e);
+                continue;
+            }
+        };
+        for draft in expired {
+            let notice = CreateMessage::new().content(format!(
+                "⌛ <@{}> your draft request for **{}** was discarded after {} minutes without changes. \
+                Start again with `/request start`.",
+                draft.author, draft.product, idle_minutes
+            ));
+            if let Err(e) = draft.channel_id.send_message(&http, notice).await {
+                println!("❌ Failed to send draft expiry notice: {:?}", e);
+            }
+        }
+    }
+}
+
+// Nudge the threads of requests coming up on their due date
+async fn remind_due_requests(
+    http: Arc<serenity::Http>,
+    store: Arc<dyn LedgerStore>,
+    reminder_hours: Vec<u64>,
+) {
+    let mut interval = tokio::time::interval(REMINDER_INTERVAL);
+    loop {
+        interval.tick().await;
+        if let Err(e) = send_due_reminders(&http, store.as_ref(), &reminder_hours).await {
+            println!("❌ Failed to send due date reminders: {}", e);
+        }
+    }
+}
 
 #[tokio::main]
 async fn main() -> Result<(), BotError> {
     dotenv().ok();
 
-    let token = var("DISCORD_TOKEN").expect("Expected DISCORD_TOKEN in env");
+    let (config, store, recipes, catalog) = match startup().await {
+        Ok(ready) => ready,
+        Err(e) => {
+            eprintln!("❌ {}", e);
+            std::process::exit(1);
+        }
+    };
+    let token = config.discord_token.clone();
     let intents = serenity::GatewayIntents::non_privileged();
 
     let options = poise::FrameworkOptions {
-        commands: vec![submit(), request()],
+        commands: vec![
+            submit(),
+            submit_bulk(),
+            withdraw(),
+            adjust(),
+            ledger(),
+            request(),
+            // Spelled out: `catalog` is also the loaded catalog here
+            commands::catalog::catalog(),
+        ],
         event_handler: |ctx, event, framework, data| {
             Box::pin(event_handler(ctx, event, framework, data))
         },
@@ -32,16 +156,26 @@ async fn main() -> Result<(), BotError> {
 
     let framework = poise::Framework::builder()
         .options(options)
-        .setup(|ctx, _ready, framework| {
+        .setup(move |ctx, _ready, framework| {
             Box::pin(async move {
                 let http = &ctx.http;
-                let guild_id: u64 = var("GUILD_ID")?.parse()?;
-                let guild = GuildId::new(guild_id);
+                let guild = config.guild_id;
                 // TODO: Remove duplicate slash functions throwing errors
                 // *For de-registering leftover global commands:
                 // Command::set_global_commands(&ctx.http, Vec::new()).await?;
                 register_in_guild(http, &framework.options().commands, guild).await?;
-                Ok(Data {})
+                tokio::spawn(expire_drafts(ctx.http.clone(), config.draft_idle_minutes));
+                tokio::spawn(remind_due_requests(
+                    ctx.http.clone(),
+                    store.clone(),
+                    config.reminder_hours.clone(),
+                ));
+                Ok(Data {
+                    config,
+                    store,
+                    recipes,
+                    catalog,
+                })
             })
         })
         .build();
@@ -58,86 +192,65 @@ async fn event_handler(
     ctx: &serenity::Context,
     event: &serenity::FullEvent,
     _framework: poise::FrameworkContext<'_, Data, BotError>,
-    _data: &Data,
+    data: &Data,
 ) -> Result<(), BotError> {
-    match event {
-        // Login event demo
-        // serenity::FullEvent::Ready { data_about_bot, .. } => {
-        //     println!("Logged in as {}", data_about_bot.user.name);
-        // }
-
-        // *This is where you catch *all* other interactions,
-        // *including button clicks:
-        serenity::FullEvent::InteractionCreate { interaction } => {
-            if let serenity::Interaction::Component(comp) = interaction.clone() {
-                if comp.data.custom_id.starts_with("request_update") {
-                    // ! THIS PREVENTS THE TIMEOUT!!!!
-                    comp.defer(&ctx.http).await?;
-
-                    let request_id = comp.data.custom_id["request_update:".len()..].to_string();
-
-                    let inventory = load_inventory_from_sheets().await?;
-                    let result = load_request_from_sheets(&request_id).await;
-                    let (product_name, request_resources, thread_id) = result?;
-
-                    let mut completed = Vec::new();
-                    let mut remaining = Vec::new();
-
-                    for (normalized_name, needed_amt) in &request_resources {
-                        let stock_amt = inventory.get(normalized_name).copied().unwrap_or(0);
-
-                        if stock_amt >= *needed_amt {
-                            completed.push(format!("• {} x {}", needed_amt, normalized_name));
-                        } else {
-                            remaining.push(format!(
-                                "• {} x {}",
-                                needed_amt - stock_amt,
-                                normalized_name
-                            ));
-                        }
-                    }
-
-                    let embed = CreateEmbed::new()
-                        .title(format!("🔷 CRAFTING REQUEST: {}", product_name))
-                        .field(
-                            "✅ In Stock:",
-                            if completed.is_empty() {
-                                "Nothing yet...".into()
-                            } else {
-                                completed.join("\n")
-                            },
-                            false,
-                        )
-                        .field(
-                            "🛠 Remaining Materials:",
-                            if remaining.is_empty() {
-                                "All materials collected! 🎉".into()
-                            } else {
-                                remaining.join("\n")
-                            },
-                            false,
-                        );
-
-                    let msg = CreateMessage::new().embed(embed);
-
-                    let response = thread_id.send_message(&ctx.http, msg).await;
-                    match response {
-                        Ok(_) => (),
-                        Err(e) => {
-                            println!("❌ Error sending to thread: {:?}", e);
-                            use std::io::Write;
-                            std::io::stdout().flush().unwrap();
-                        }
-                    }
-                } else if comp.data.custom_id.starts_with("request_complete") {
-                    comp.defer(&ctx.http).await?;
-                    let request_id = comp.data.custom_id["request_complete:".len()..].to_string();
-                    complete_request(&ctx, &comp, &request_id).await?;
-                }
-            }
-        }
+    // Login event demo
+    // if let serenity::FullEvent::Ready { data_about_bot, .. } = event {
+    //     println!("Logged in as {}", data_about_bot.user.name);
+    // }
+
+    // *This is where you catch *all* other interactions,
+    // *including button clicks:
+    if let serenity::FullEvent::InteractionCreate { interaction } = event
+        && let serenity::Interaction::Component(comp) = interaction.clone()
+    {
+        handle_component(ctx, comp, data).await?;
+    }
+    Ok(())
+}
+
+async fn handle_component(
+    ctx: &serenity::Context,
+    comp: serenity::ComponentInteraction,
+    data: &Data,
+) -> Result<(), BotError> {
+    if comp.data.custom_id.starts_with("request_update") {
+        // ! THIS PREVENTS THE TIMEOUT!!!!
+        comp.defer(&ctx.http).await?;
 
-        _ => {}
+        let request_id = comp.data.custom_id["request_update:".len()..].to_string();
+
+        let request_lines = data.store.request_lines(&request_id).await?;
+        let thread_id = request_lines
+            .first()
+            .ok_or("No thread ID found for request")?
+            .thread_id;
+        let entries = data.store.ledger_entries().await?;
+        let embed = request_status_embed(
+            &request_lines,
+            &entries,
+            &data.config.conversions,
+            &data.catalog,
+        );
+
+        let msg = CreateMessage::new().embed(embed);
+
+        let response = thread_id.send_message(&ctx.http, msg).await;
+        if let Err(e) = response {
+            println!("❌ Error sending to thread: {:?}", e);
+            use std::io::Write;
+            std::io::stdout().flush().unwrap();
+        }
+    } else if comp.data.custom_id.starts_with("request_complete") {
+        comp.defer(&ctx.http).await?;
+        let request_id = comp.data.custom_id["request_complete:".len()..].to_string();
+        complete_request(ctx, &comp, data.store.as_ref(), &request_id).await?;
+    } else if let Some(request_id) = comp.data.custom_id.strip_prefix("request_contribute:") {
+        // Answered with a form instead of a defer
+        let request_id = request_id.to_string();
+        let (limits, conversions) = (&data.config.submit_limits, &data.config.conversions);
+        let (store, catalog) = (data.store.as_ref(), &data.catalog);
+        contribute_to_request(ctx, comp, store, catalog, limits, conversions, &request_id).await?;
     }
     Ok(())
 }
@@ -0,0 +1,329 @@
+use super::{Inventory, LedgerStore, RequestRow, RequestStatus};
+use crate::BotError;
+use crate::utils::resources::normalize_resource_key;
+use std::cmp::Reverse;
+use tokio::sync::Mutex;
+
+/// Held by anything that changes stock or request lines and then rebalances, so two
+/// commands can't hand out the same stock at once
+pub static ALLOCATION_LOCK: Mutex<()> = Mutex::const_new(());
+
+/// A request line whose reservation went up (new stock) or down (stock gone, line closed)
+#[derive(Clone, Debug)]
+pub struct ReservationChange {
+    pub request_id: String,
+    pub product: String,
+    /// The line's resource, as written on the request
+    pub resource: String,
+    pub delta: i64,
+}
+
+/// Stock left once every open request line has taken its reservation, by normalized name
+pub fn available_inventory(inventory: &Inventory, rows: &[RequestRow]) -> Inventory {
+    let mut available = inventory.clone();
+    for row in rows
+        .iter()
+        .filter(|row| row.status == RequestStatus::InProgress)
+    {
+        if let Some(stock) = available.get_mut(&normalize_resource_key(&row.resource)) {
+            *stock = stock.saturating_sub(row.reserved);
+        }
+    }
+    available
+}
+
+/// Bring the reservations on `resources` (normalized names) in line with `inventory`:
+/// 1. lines that are no longer open, or reserve more than they need, give the excess back
+/// 2. if stock fell below what's reserved, it is released in reverse queue order
+/// 3. stock nobody holds goes to open lines still short, in queue order
+///
+/// Queue order is highest priority first, then oldest line first. A `preferred`
+/// `(request_id, cap)` jumps the queue for up to `cap` of each resource, which is how a
+/// submission made for one request reaches it.
+///
+/// Returns the index into `rows` and reservation delta of every line that changed.
+pub fn rebalance(
+    inventory: &Inventory,
+    rows: &mut [RequestRow],
+    resources: &[String],
+    preferred: Option<(&str, u64)>,
+) -> Vec<(usize, i64)> {
+    let mut deltas = vec![0i64; rows.len()];
+    for resource in resources {
+        let mut matching: Vec<usize> = (0..rows.len())
+            .filter(|&idx| normalize_resource_key(&rows[idx].resource) == *resource)
+            .collect();
+        matching.sort_by_key(|&idx| (Reverse(rows[idx].priority), idx));
+        let mut preferred_cap = 0;
+        if let Some((request_id, cap)) = preferred
+            && let Some(pos) = matching
+                .iter()
+                .position(|&idx| rows[idx].request_id == request_id)
+        {
+            let idx = matching.remove(pos);
+            matching.insert(0, idx);
+            preferred_cap = cap;
+        }
+
+        let mut reserved_total = 0;
+        for &idx in &matching {
+            let row = &mut rows[idx];
+            let keep = match row.status {
+                RequestStatus::InProgress => row.reserved.min(row.amount),
+                _ => 0,
+            };
+            deltas[idx] -= (row.reserved - keep) as i64;
+            row.reserved = keep;
+            reserved_total += keep;
+        }
+
+        let stock = inventory.get(resource).copied().unwrap_or(0);
+        if reserved_total > stock {
+            let mut excess = reserved_total - stock;
+            for &idx in matching.iter().rev() {
+                let released = excess.min(rows[idx].reserved);
+                rows[idx].reserved -= released;
+                deltas[idx] -= released as i64;
+                excess -= released;
+            }
+        } else {
+            let mut free = stock - reserved_total;
+            for (position, &idx) in matching.iter().enumerate() {
+                let row = &mut rows[idx];
+                if row.status != RequestStatus::InProgress {
+                    continue;
+                }
+                let mut granted = free.min(row.amount - row.reserved);
+                if position == 0 && preferred_cap > 0 {
+                    granted = granted.min(preferred_cap);
+                }
+                row.reserved += granted;
+                deltas[idx] += granted as i64;
+                free -= granted;
+            }
+        }
+    }
+
+    deltas
+        .into_iter()
+        .enumerate()
+        .filter(|&(_, delta)| delta != 0)
+        .collect()
+}
+
+/// Rebalance `resources` against the stored inventory and save the lines that changed.
+/// Callers hold `ALLOCATION_LOCK` across their own change and this call.
+pub async fn rebalance_store(
+    store: &dyn LedgerStore,
+    resources: &[String],
+    preferred: Option<(&str, u64)>,
+) -> Result<Vec<ReservationChange>, BotError> {
+    let inventory = store.inventory().await?;
+    let mut rows = store.request_rows().await?;
+    let changed = rebalance(&inventory, &mut rows, resources, preferred);
+    if changed.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let updated: Vec<RequestRow> = changed.iter().map(|&(idx, _)| rows[idx].clone()).collect();
+    store.update_request_rows(&updated).await?;
+    Ok(changed
+        .into_iter()
+        .map(|(idx, delta)| ReservationChange {
+            request_id: rows[idx].request_id.clone(),
+            product: rows[idx].product.clone(),
+            resource: rows[idx].resource.clone(),
+            delta,
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::ledger::{RevertOutcome, revert_transaction};
+    use crate::commands::submit::record_submission;
+    use crate::store::{LedgerEntry, MemoryStore, RequestPriority};
+    use poise::serenity_prelude::{ChannelId, User, UserId};
+
+    fn line(request_id: &str, amount: u64, priority: RequestPriority) -> RequestRow {
+        RequestRow {
+            request_id: request_id.to_string(),
+            product: format!("Product {}", request_id),
+            resource: "Iron Ore".to_string(),
+            amount,
+            reserved: 0,
+            status: RequestStatus::InProgress,
+            thread_id: ChannelId::new(1),
+            requester: None,
+            priority,
+            due: None,
+            last_reminder: None,
+            crafter: None,
+            claims: Vec::new(),
+        }
+    }
+
+    fn member(id: u64) -> User {
+        let mut user = User::default();
+        user.id = UserId::new(id);
+        user.name = format!("member{}", id);
+        user
+    }
+
+    async fn store_with(stock: i64, lines: &[RequestRow]) -> MemoryStore {
+        let store = MemoryStore::default();
+        store
+            .adjust_inventory(&[("iron ore".to_string(), stock)])
+            .await
+            .unwrap();
+        store.append_request_rows(lines).await.unwrap();
+        store
+    }
+
+    async fn reserved(store: &MemoryStore) -> Vec<(String, u64)> {
+        store
+            .request_rows()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|row| (row.request_id, row.reserved))
+            .collect()
+    }
+
+    fn pairs(expected: &[(&str, u64)]) -> Vec<(String, u64)> {
+        expected
+            .iter()
+            .map(|&(id, amount)| (id.to_string(), amount))
+            .collect()
+    }
+
+    fn iron() -> Vec<String> {
+        vec!["iron ore".to_string()]
+    }
+
+    #[tokio::test]
+    async fn higher_priority_first_then_oldest() {
+        let store = store_with(
+            1000,
+            &[
+                line("a", 800, RequestPriority::Normal),
+                line("b", 800, RequestPriority::Normal),
+                line("c", 800, RequestPriority::Urgent),
+            ],
+        )
+        .await;
+        rebalance_store(&store, &iron(), None).await.unwrap();
+        assert_eq!(
+            reserved(&store).await,
+            pairs(&[("a", 200), ("b", 0), ("c", 800)])
+        );
+    }
+
+    #[tokio::test]
+    async fn preferred_request_gets_at_most_its_cap() {
+        let store = store_with(
+            1000,
+            &[
+                line("a", 1000, RequestPriority::Normal),
+                line("b", 1000, RequestPriority::Normal),
+            ],
+        )
+        .await;
+        rebalance_store(&store, &iron(), Some(("b", 300)))
+            .await
+            .unwrap();
+        assert_eq!(reserved(&store).await, pairs(&[("a", 700), ("b", 300)]));
+    }
+
+    #[tokio::test]
+    async fn stock_drop_releases_from_the_back_of_the_queue() {
+        let store = store_with(
+            1000,
+            &[
+                line("a", 500, RequestPriority::High),
+                line("b", 500, RequestPriority::Normal),
+            ],
+        )
+        .await;
+        rebalance_store(&store, &iron(), None).await.unwrap();
+        store
+            .adjust_inventory(&[("iron ore".to_string(), -400)])
+            .await
+            .unwrap();
+        let changes = rebalance_store(&store, &iron(), None).await.unwrap();
+        assert_eq!(reserved(&store).await, pairs(&[("a", 500), ("b", 100)]));
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].delta, -400);
+    }
+
+    #[tokio::test]
+    async fn closed_lines_give_their_stock_to_the_queue() {
+        let store = store_with(
+            500,
+            &[
+                line("a", 500, RequestPriority::Normal),
+                line("b", 500, RequestPriority::Normal),
+            ],
+        )
+        .await;
+        rebalance_store(&store, &iron(), None).await.unwrap();
+        let mut rows = store.request_rows().await.unwrap();
+        rows[0].status = RequestStatus::Cancelled;
+        store.update_request_rows(&rows[..1]).await.unwrap();
+        rebalance_store(&store, &iron(), None).await.unwrap();
+        assert_eq!(reserved(&store).await, pairs(&[("a", 0), ("b", 500)]));
+    }
+
+    #[tokio::test]
+    async fn revert_releases_the_requests_it_fed() {
+        let store = store_with(
+            0,
+            &[
+                line("a", 1000, RequestPriority::Normal),
+                line("b", 1000, RequestPriority::Normal),
+            ],
+        )
+        .await;
+        record_submission(&store, "Iron Ore", 1000, &member(1), Some("a"))
+            .await
+            .unwrap();
+        let second = record_submission(&store, "Iron Ore", 1000, &member(2), Some("b"))
+            .await
+            .unwrap();
+        assert_eq!(reserved(&store).await, pairs(&[("a", 1000), ("b", 1000)]));
+
+        // b now outranks a, so releasing by queue order would take a's stock instead
+        let mut rows = store.request_rows().await.unwrap();
+        rows[1].priority = RequestPriority::Urgent;
+        store.update_request_rows(&rows[1..]).await.unwrap();
+
+        let outcome = revert_transaction(&store, &second.txn_id, &member(3))
+            .await
+            .unwrap();
+        let RevertOutcome::Reverted { released, .. } = outcome else {
+            panic!("revert was refused");
+        };
+        assert_eq!(released, vec!["Product b".to_string()]);
+        assert_eq!(reserved(&store).await, pairs(&[("a", 1000), ("b", 0)]));
+        assert_eq!(store.inventory().await.unwrap()["iron ore"], 1000);
+    }
+
+    #[tokio::test]
+    async fn blank_ids_never_match_old_rows() {
+        let store = store_with(500, &[]).await;
+        // A row written before transactions had IDs
+        let mut legacy = LedgerEntry::new("Iron Ore", 500, &member(1), None);
+        legacy.txn_id = String::new();
+        store.append_ledger(&[legacy]).await.unwrap();
+
+        for txn_id in ["", "   "] {
+            let outcome = revert_transaction(&store, txn_id, &member(2))
+                .await
+                .unwrap();
+            assert!(matches!(outcome, RevertOutcome::NotFound), "{:?}", txn_id);
+        }
+        assert_eq!(store.inventory().await.unwrap()["iron ore"], 500);
+        assert_eq!(store.ledger_entries().await.unwrap().len(), 1);
+    }
+}
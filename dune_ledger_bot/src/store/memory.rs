@@ -0,0 +1,57 @@
+use super::{Inventory, InventoryAdjustment, LedgerEntry, LedgerStore, RequestRow, apply_deltas};
+use crate::BotError;
+use async_trait::async_trait;
+use std::sync::Mutex;
+
+/// Process-local store. Nothing survives a restart, which makes it handy for
+/// trying the bot out and for exercising command logic without Google.
+#[derive(Default)]
+pub struct MemoryStore {
+    inventory: Mutex<Inventory>,
+    ledger: Mutex<Vec<LedgerEntry>>,
+    requests: Mutex<Vec<RequestRow>>,
+}
+
+#[async_trait]
+impl LedgerStore for MemoryStore {
+    async fn inventory(&self) -> Result<Inventory, BotError> {
+        Ok(self.inventory.lock().unwrap().clone())
+    }
+
+    async fn adjust_inventory(
+        &self,
+        deltas: &[(String, i64)],
+    ) -> Result<InventoryAdjustment, BotError> {
+        Ok(apply_deltas(&mut self.inventory.lock().unwrap(), deltas))
+    }
+
+    async fn append_ledger(&self, entries: &[LedgerEntry]) -> Result<(), BotError> {
+        self.ledger.lock().unwrap().extend_from_slice(entries);
+        Ok(())
+    }
+
+    async fn ledger_entries(&self) -> Result<Vec<LedgerEntry>, BotError> {
+        Ok(self.ledger.lock().unwrap().clone())
+    }
+
+    async fn request_rows(&self) -> Result<Vec<RequestRow>, BotError> {
+        Ok(self.requests.lock().unwrap().clone())
+    }
+
+    async fn append_request_rows(&self, rows: &[RequestRow]) -> Result<(), BotError> {
+        self.requests.lock().unwrap().extend_from_slice(rows);
+        Ok(())
+    }
+
+    async fn update_request_rows(&self, rows: &[RequestRow]) -> Result<(), BotError> {
+        let mut stored = self.requests.lock().unwrap();
+        for row in rows {
+            for existing in stored.iter_mut() {
+                if existing.request_id == row.request_id && existing.resource == row.resource {
+                    *existing = row.clone();
+                }
+            }
+        }
+        Ok(())
+    }
+}
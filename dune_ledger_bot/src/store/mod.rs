@@ -0,0 +1,432 @@
+pub mod allocation;
+pub mod memory;
+pub mod sheets;
+pub mod sqlite;
+
+use crate::BotError;
+use crate::config::StoreConfig;
+use crate::utils::resources::normalize_resource_key;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use poise::serenity_prelude::{ChannelId, User, UserId};
+use std::collections::HashMap;
+use std::sync::Arc;
+use uuid::Uuid;
+
+pub use memory::MemoryStore;
+pub use sheets::SheetsStore;
+pub use sqlite::SqliteStore;
+
+/// Live stock keyed by normalized resource name (see `normalize_resource_key`)
+pub type Inventory = HashMap<String, u64>;
+
+/// One transaction row in the Ledger
+#[derive(Clone, Debug, PartialEq)]
+pub struct LedgerEntry {
+    pub resource: String,
+    pub amount: i64,
+    pub date: String,
+    /// Name of whoever recorded it, as it was at the time
+    pub user: String,
+    /// Who recorded it (unknown on rows written before it was recorded)
+    pub user_id: Option<UserId>,
+    /// Why stock was taken out or corrected; empty for plain submissions
+    pub reason: Option<String>,
+    /// Short ID members quote to `/ledger revert` (empty on rows written before IDs existed)
+    pub txn_id: String,
+    /// The transaction this entry compensates, if it is a revert
+    pub reverts: Option<String>,
+    /// `(request_id, amount)` of this entry's stock handed to request lines
+    pub allocations: Vec<(String, u64)>,
+}
+
+impl LedgerEntry {
+    /// A transaction `user` makes now, with a fresh ID
+    pub fn new(resource: &str, amount: i64, user: &User, reason: Option<String>) -> Self {
+        LedgerEntry {
+            resource: resource.to_string(),
+            amount,
+            date: Utc::now().to_rfc3339(),
+            user: user.name.clone(),
+            user_id: Some(user.id),
+            reason,
+            txn_id: new_txn_id(),
+            reverts: None,
+            allocations: Vec::new(),
+        }
+    }
+
+    /// Who to credit in messages: a mention, or the bare name on rows written before
+    /// user ids were recorded. Also what contributions are grouped by, so a member who
+    /// renames keeps their history.
+    pub fn contributor(&self) -> String {
+        match self.user_id {
+            Some(id) => format!("<@{}>", id),
+            None => self.user.clone(),
+        }
+    }
+}
+
+fn new_txn_id() -> String {
+    Uuid::new_v4().simple().to_string()[..8].to_string()
+}
+
+/// `"key:amount;key:amount"`, the form allocations take in a single cell
+pub fn format_amounts(allocations: &[(String, u64)]) -> String {
+    allocations
+        .iter()
+        .map(|(request_id, amount)| format!("{}:{}", request_id, amount))
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+pub fn parse_amounts(raw: &str) -> Vec<(String, u64)> {
+    raw.split(';')
+        .filter_map(|pair| {
+            let (request_id, amount) = pair.trim().rsplit_once(':')?;
+            Some((request_id.to_string(), amount.parse().ok()?))
+        })
+        .collect()
+}
+
+/// Claims in the same form, keyed by user id
+pub fn format_claims(claims: &[(UserId, u64)]) -> String {
+    claims
+        .iter()
+        .map(|(user, amount)| format!("{}:{}", user, amount))
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+/// Claims stored by `format_claims`; anything not keyed by a user id is skipped
+pub fn parse_claims(raw: &str) -> Vec<(UserId, u64)> {
+    parse_amounts(raw)
+        .into_iter()
+        .filter_map(|(user, amount)| Some((UserId::new(user.parse().ok()?), amount)))
+        .collect()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RequestStatus {
+    InProgress,
+    Completed,
+    Cancelled,
+}
+
+impl RequestStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RequestStatus::InProgress => "in_progress",
+            RequestStatus::Completed => "completed",
+            RequestStatus::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim() {
+            "in_progress" => Some(RequestStatus::InProgress),
+            "completed" => Some(RequestStatus::Completed),
+            "cancelled" => Some(RequestStatus::Cancelled),
+            _ => None,
+        }
+    }
+}
+
+/// How soon a request should get stock; incoming submissions are reserved for higher
+/// priorities first
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, poise::ChoiceParameter)]
+pub enum RequestPriority {
+    Low,
+    #[default]
+    Normal,
+    High,
+    Urgent,
+}
+
+impl RequestPriority {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RequestPriority::Low => "low",
+            RequestPriority::Normal => "normal",
+            RequestPriority::High => "high",
+            RequestPriority::Urgent => "urgent",
+        }
+    }
+
+    /// Blank (rows written before priorities existed) counts as normal
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim() {
+            "low" => Some(RequestPriority::Low),
+            "normal" | "" => Some(RequestPriority::Normal),
+            "high" => Some(RequestPriority::High),
+            "urgent" => Some(RequestPriority::Urgent),
+            _ => None,
+        }
+    }
+}
+
+/// One material line of a crafting request. A request is every row sharing a `request_id`,
+/// and a line is identified by its `(request_id, resource)` pair.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RequestRow {
+    pub request_id: String,
+    pub product: String,
+    pub resource: String,
+    /// How much the request needs in total
+    pub amount: u64,
+    /// How much of the inventory is set aside for this line (see `allocation`)
+    pub reserved: u64,
+    pub status: RequestStatus,
+    pub thread_id: ChannelId,
+    /// Who ran `/request finish` (unknown on rows written before it was recorded)
+    pub requester: Option<UserId>,
+    pub priority: RequestPriority,
+    /// When the requester needs it by
+    pub due: Option<DateTime<Utc>>,
+    /// Hours-before-due of the last reminder posted in the thread
+    pub last_reminder: Option<u64>,
+    /// Who is going to craft the product
+    pub crafter: Option<UserId>,
+    /// `(member, amount)` members promised to bring of this line
+    pub claims: Vec<(UserId, u64)>,
+}
+
+/// What happened to a call to `LedgerStore::adjust_inventory`
+#[derive(Debug)]
+pub enum InventoryAdjustment {
+    /// Every delta was applied; this is the inventory right afterwards
+    Applied(Inventory),
+    /// Nothing was changed, because these resources (with their current stock)
+    /// would have gone below zero
+    Insufficient(Vec<(String, u64)>),
+}
+
+/// Apply `(resource, delta)` pairs to `inventory` in place, all or nothing
+pub fn apply_deltas(inventory: &mut Inventory, deltas: &[(String, i64)]) -> InventoryAdjustment {
+    let mut updated = inventory.clone();
+    let mut short = Vec::new();
+    for (resource, delta) in deltas {
+        let key = normalize_resource_key(resource);
+        let stock = updated.entry(key.clone()).or_insert(0);
+        match stock.checked_add_signed(*delta) {
+            Some(new_stock) => *stock = new_stock,
+            None => short.push((key, *stock)),
+        }
+    }
+
+    if !short.is_empty() {
+        return InventoryAdjustment::Insufficient(short);
+    }
+    *inventory = updated;
+    InventoryAdjustment::Applied(inventory.clone())
+}
+
+/// Everything the commands need to persist: inventory, ledger transactions and request lines.
+/// Implementations own their layout (sheet ranges, tables, ...) so command code never does.
+#[async_trait]
+pub trait LedgerStore: Send + Sync {
+    async fn inventory(&self) -> Result<Inventory, BotError>;
+
+    /// Atomically add each `(resource, delta)` to the stock, against the stock as it is
+    /// right now rather than a copy read earlier. Concurrent adjustments are serialized
+    /// (each guild gets its own store), so none of them are ever lost.
+    async fn adjust_inventory(
+        &self,
+        deltas: &[(String, i64)],
+    ) -> Result<InventoryAdjustment, BotError>;
+
+    async fn append_ledger(&self, entries: &[LedgerEntry]) -> Result<(), BotError>;
+
+    async fn ledger_entries(&self) -> Result<Vec<LedgerEntry>, BotError>;
+
+    async fn request_rows(&self) -> Result<Vec<RequestRow>, BotError>;
+
+    async fn append_request_rows(&self, rows: &[RequestRow]) -> Result<(), BotError>;
+
+    /// Overwrite the stored lines matching each row's `(request_id, resource)`
+    async fn update_request_rows(&self, rows: &[RequestRow]) -> Result<(), BotError>;
+
+    /// All lines of a single request, in stored order
+    async fn request_lines(&self, request_id: &str) -> Result<Vec<RequestRow>, BotError> {
+        Ok(self
+            .request_rows()
+            .await?
+            .into_iter()
+            .filter(|row| row.request_id == request_id)
+            .collect())
+    }
+}
+
+/// Open the backend chosen in the configuration
+pub async fn open(config: &StoreConfig) -> Result<Arc<dyn LedgerStore>, BotError> {
+    Ok(match config {
+        StoreConfig::Sheets(sheets) => Arc::new(SheetsStore::connect(sheets).await?),
+        StoreConfig::Sqlite { path } => Arc::new(SqliteStore::open(path)?),
+        StoreConfig::Memory => Arc::new(MemoryStore::default()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    // Every backend but Sheets, which needs a Google account
+    fn stores() -> Vec<(&'static str, Box<dyn LedgerStore>)> {
+        vec![
+            ("memory", Box::new(MemoryStore::default())),
+            ("sqlite", Box::new(SqliteStore::open(":memory:").unwrap())),
+        ]
+    }
+
+    fn deltas(pairs: &[(&str, i64)]) -> Vec<(String, i64)> {
+        pairs
+            .iter()
+            .map(|&(resource, delta)| (resource.to_string(), delta))
+            .collect()
+    }
+
+    fn row(resource: &str) -> RequestRow {
+        RequestRow {
+            request_id: "req".to_string(),
+            product: "Stillsuit".to_string(),
+            resource: resource.to_string(),
+            amount: 1200,
+            reserved: 300,
+            status: RequestStatus::InProgress,
+            thread_id: ChannelId::new(42),
+            requester: Some(UserId::new(7)),
+            priority: RequestPriority::High,
+            due: Some(Utc.with_ymd_and_hms(2026, 3, 1, 18, 30, 0).unwrap()),
+            last_reminder: Some(48),
+            crafter: Some(UserId::new(8)),
+            claims: vec![(UserId::new(9), 100), (UserId::new(10), 50)],
+        }
+    }
+
+    #[test]
+    fn deltas_apply_all_or_nothing() {
+        let mut inventory = Inventory::from([("iron ore".to_string(), 10)]);
+        let InventoryAdjustment::Insufficient(short) = apply_deltas(
+            &mut inventory,
+            &deltas(&[("Iron Ore", -3), ("Copper Ore", -1)]),
+        ) else {
+            panic!("copper ore went negative");
+        };
+        assert_eq!(short, vec![("copper ore".to_string(), 0)]);
+        assert_eq!(inventory, Inventory::from([("iron ore".to_string(), 10)]));
+    }
+
+    #[tokio::test]
+    async fn adjustments_going_negative_change_nothing() {
+        for (name, store) in stores() {
+            store
+                .adjust_inventory(&deltas(&[("Iron Ore", 10), ("Copper Ore", 5)]))
+                .await
+                .unwrap();
+            let adjustment = store
+                .adjust_inventory(&deltas(&[("Iron Ore", -3), ("copper ore", -6)]))
+                .await
+                .unwrap();
+            assert!(
+                matches!(&adjustment, InventoryAdjustment::Insufficient(short)
+                    if *short == vec![("copper ore".to_string(), 5)]),
+                "{}: {:?}",
+                name,
+                adjustment
+            );
+            assert_eq!(
+                store.inventory().await.unwrap(),
+                Inventory::from([("iron ore".to_string(), 10), ("copper ore".to_string(), 5)]),
+                "{}",
+                name
+            );
+
+            let InventoryAdjustment::Applied(updated) = store
+                .adjust_inventory(&deltas(&[("Iron Ore", -3), ("IRON ORE", -7)]))
+                .await
+                .unwrap()
+            else {
+                panic!("{}: exactly all the iron ore can be taken", name);
+            };
+            assert_eq!(updated["iron ore"], 0, "{}", name);
+            assert_eq!(store.inventory().await.unwrap(), updated, "{}", name);
+        }
+    }
+
+    #[tokio::test]
+    async fn request_rows_round_trip() {
+        for (name, store) in stores() {
+            // A request with every field set, and a bare one with nothing optional
+            let mut rows = vec![row("Iron Ore"), row("Copper Ore")];
+            let bare = RequestRow {
+                request_id: "bare".to_string(),
+                requester: None,
+                priority: RequestPriority::Normal,
+                due: None,
+                last_reminder: None,
+                crafter: None,
+                claims: Vec::new(),
+                ..row("Iron Ore")
+            };
+            rows.push(bare);
+            store.append_request_rows(&rows).await.unwrap();
+            assert_eq!(store.request_rows().await.unwrap(), rows, "{}", name);
+
+            // Request-wide fields are the same on every line of a request
+            for row in rows[..2].iter_mut() {
+                row.status = RequestStatus::Completed;
+                row.last_reminder = Some(12);
+                row.crafter = Some(UserId::new(11));
+            }
+            rows[0].reserved = 1200;
+            rows[0].claims = vec![(UserId::new(9), 1200)];
+            store.update_request_rows(&rows[..2]).await.unwrap();
+            assert_eq!(
+                store.request_lines("req").await.unwrap(),
+                rows[..2],
+                "{}",
+                name
+            );
+            assert_eq!(
+                store.request_lines("bare").await.unwrap(),
+                rows[2..],
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn ledger_entries_round_trip() {
+        for (name, store) in stores() {
+            let mut user = User::default();
+            user.id = UserId::new(7);
+            user.name = "paul".to_string();
+            let mut submission = LedgerEntry::new("Iron Ore", 500, &user, None);
+            submission.allocations = vec![("req".to_string(), 300), ("other".to_string(), 200)];
+            let mut revert = LedgerEntry::new(
+                "Iron Ore",
+                -500,
+                &user,
+                Some(format!("Revert of {}", submission.txn_id)),
+            );
+            revert.reverts = Some(submission.txn_id.clone());
+            let mut legacy = LedgerEntry::new("Copper Ore", 20, &user, None);
+            legacy.user_id = None;
+            legacy.txn_id = String::new();
+
+            let entries = vec![submission, revert, legacy];
+            store.append_ledger(&entries).await.unwrap();
+            assert_eq!(store.ledger_entries().await.unwrap(), entries, "{}", name);
+        }
+    }
+
+    #[test]
+    fn claims_are_keyed_by_user_id() {
+        let claims = vec![(UserId::new(9), 100), (UserId::new(10), 50)];
+        assert_eq!(parse_claims(&format_claims(&claims)), claims);
+        // Claims from before ids were stored name the member instead
+        assert_eq!(parse_claims("paul:100;10:50"), claims[1..]);
+    }
+}
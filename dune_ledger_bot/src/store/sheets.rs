@@ -0,0 +1,387 @@
+use super::{
+    Inventory, InventoryAdjustment, LedgerEntry, LedgerStore, RequestPriority, RequestRow,
+    RequestStatus, apply_deltas, format_amounts, format_claims, parse_amounts, parse_claims,
+};
+use crate::BotError;
+use crate::config::{ConfigError, SheetsConfig};
+use crate::utils::resources::normalize_resource_key;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use google_sheets4 as sheets4;
+use hyper_util::client::legacy::connect::HttpConnector;
+use poise::serenity_prelude::{ChannelId, UserId};
+use serde_json::Value;
+use sheets4::{Sheets, api::ValueRange, hyper_rustls, yup_oauth2};
+use tokio::sync::Mutex;
+
+const INVENTORY_COLUMNS: &str = "A:B";
+const LEDGER_COLUMNS: &str = "A:I";
+const REQUEST_COLUMNS: &str = "A:M";
+const SPREADSHEETS_SCOPE: &str = "https://www.googleapis.com/auth/spreadsheets";
+
+type Hub = Sheets<hyper_rustls::HttpsConnector<HttpConnector>>;
+
+/// The original three-spreadsheet layout (tabs are configurable, columns are not):
+/// - Inventory `A:B` → resource, amount
+/// - Ledger `A:I` → resource, amount, date, user, reason, txn id, reverted txn id, allocations,
+///   user id
+/// - Request `A:M` → request id, product, resource, amount, status, thread id, requester,
+///   reserved, priority, due date, last reminder (hours before due), claims, crafter
+pub struct SheetsStore {
+    // Built once at startup; the authenticator caches and refreshes its token
+    hub: Hub,
+    // Sheets has no transactions, so every read-modify-write of a range holds this.
+    // The bot is the only writer, which makes that enough to never lose an update.
+    write_lock: Mutex<()>,
+    inventory_sheet_id: String,
+    inventory_range: String,
+    ledger_sheet_id: String,
+    ledger_range: String,
+    request_sheet_id: String,
+    request_range: String,
+}
+
+impl SheetsStore {
+    /// Authenticate with the service account and check every sheet can be read,
+    /// so a bad credential or ID stops the bot at startup rather than mid-command
+    pub async fn connect(config: &SheetsConfig) -> Result<Self, BotError> {
+        let key_path = &config.service_account_path;
+        let service_account_key = yup_oauth2::read_service_account_key(key_path)
+            .await
+            .map_err(|e| format!("Can't read credential {}: {}", key_path.display(), e))?;
+        let authenticator = yup_oauth2::ServiceAccountAuthenticator::builder(service_account_key)
+            .build()
+            .await?;
+        authenticator
+            .token(&[SPREADSHEETS_SCOPE])
+            .await
+            .map_err(|e| format!("Google rejected the service account credential: {}", e))?;
+
+        let client =
+            hyper_util::client::legacy::Client::builder(hyper_util::rt::TokioExecutor::new())
+                .build(
+                    hyper_rustls::HttpsConnectorBuilder::new()
+                        .with_native_roots()?
+                        .https_or_http()
+                        .enable_http1()
+                        .build(),
+                );
+
+        let store = SheetsStore {
+            hub: Sheets::new(client, authenticator),
+            write_lock: Mutex::new(()),
+            inventory_sheet_id: config.inventory.spreadsheet_id.clone(),
+            inventory_range: config.inventory.range(INVENTORY_COLUMNS),
+            ledger_sheet_id: config.ledger.spreadsheet_id.clone(),
+            ledger_range: config.ledger.range(LEDGER_COLUMNS),
+            request_sheet_id: config.request.spreadsheet_id.clone(),
+            request_range: config.request.range(REQUEST_COLUMNS),
+        };
+
+        let mut problems = Vec::new();
+        for (name, sheet_id, range) in [
+            (
+                "Inventory",
+                &store.inventory_sheet_id,
+                &store.inventory_range,
+            ),
+            ("Ledger", &store.ledger_sheet_id, &store.ledger_range),
+            ("Request", &store.request_sheet_id, &store.request_range),
+        ] {
+            if let Err(e) = store.read(sheet_id, range).await {
+                problems.push(format!(
+                    "{} sheet {} ({}) isn't reachable: {}",
+                    name, sheet_id, range, e
+                ));
+            }
+        }
+        if !problems.is_empty() {
+            return Err(ConfigError { problems }.into());
+        }
+
+        Ok(store)
+    }
+
+    async fn read(&self, sheet_id: &str, range: &str) -> Result<Vec<Vec<Value>>, BotError> {
+        let result = self
+            .hub
+            .spreadsheets()
+            .values_get(sheet_id, range)
+            .doit()
+            .await?;
+        Ok(result.1.values.unwrap_or_default())
+    }
+
+    async fn write(
+        &self,
+        sheet_id: &str,
+        range: &str,
+        values: Vec<Vec<Value>>,
+    ) -> Result<(), BotError> {
+        self.hub
+            .spreadsheets()
+            .values_update(
+                ValueRange {
+                    range: Some(range.to_string()),
+                    values: Some(values),
+                    ..Default::default()
+                },
+                sheet_id,
+                range,
+            )
+            .value_input_option("RAW")
+            .doit()
+            .await?;
+        Ok(())
+    }
+
+    async fn append(
+        &self,
+        sheet_id: &str,
+        range: &str,
+        values: Vec<Vec<Value>>,
+    ) -> Result<(), BotError> {
+        self.hub
+            .spreadsheets()
+            .values_append(
+                ValueRange {
+                    range: Some(range.to_string()),
+                    values: Some(values),
+                    ..Default::default()
+                },
+                sheet_id,
+                range,
+            )
+            .value_input_option("RAW")
+            .doit()
+            .await?;
+        Ok(())
+    }
+}
+
+// Cells come back as formatted strings, but anything we wrote as a number may not
+fn cell(row: &[Value], idx: usize) -> String {
+    match row.get(idx) {
+        Some(Value::String(s)) => s.clone(),
+        Some(Value::Null) | None => String::new(),
+        Some(other) => other.to_string(),
+    }
+}
+
+fn parse_amount(raw: &str) -> Option<u64> {
+    let cleaned = raw.trim().replace(',', "");
+    if cleaned.is_empty() {
+        return Some(0);
+    }
+    cleaned.parse::<u64>().ok()
+}
+
+fn parse_inventory(values: &[Vec<Value>]) -> Inventory {
+    let mut inventory = Inventory::new();
+    for row in values {
+        // Skips the header row, and anything else that isn't a count
+        let Some(amount) = parse_amount(&cell(row, 1)) else {
+            continue;
+        };
+        let name = normalize_resource_key(&cell(row, 0));
+        if !name.is_empty() {
+            inventory.insert(name, amount);
+        }
+    }
+    inventory
+}
+
+fn parse_ledger_row(row: &[Value]) -> Option<LedgerEntry> {
+    // The header (and any hand-written note) has no numeric amount
+    let amount = cell(row, 1).trim().replace(',', "").parse::<i64>().ok()?;
+    let optional = |idx| Some(cell(row, idx)).filter(|v| !v.is_empty());
+
+    Some(LedgerEntry {
+        resource: cell(row, 0),
+        amount,
+        date: cell(row, 2),
+        user: cell(row, 3),
+        user_id: cell(row, 8).trim().parse().ok().map(UserId::new),
+        reason: optional(4),
+        txn_id: cell(row, 5),
+        reverts: optional(6),
+        allocations: parse_amounts(&cell(row, 7)),
+    })
+}
+
+fn parse_request_row(row: &[Value]) -> Option<RequestRow> {
+    let status = RequestStatus::parse(&cell(row, 4))?;
+    let raw_thread = cell(row, 5);
+    let cleaned = raw_thread.replace(|c: char| !c.is_numeric(), "");
+    let Ok(thread_id) = cleaned.parse::<u64>() else {
+        println!("❌ Failed to parse thread ID: {:?}", raw_thread);
+        return None;
+    };
+
+    Some(RequestRow {
+        request_id: cell(row, 0),
+        product: cell(row, 1),
+        resource: cell(row, 2),
+        amount: parse_amount(&cell(row, 3))?,
+        reserved: parse_amount(&cell(row, 7))?,
+        status,
+        thread_id: ChannelId::new(thread_id),
+        requester: cell(row, 6).trim().parse().ok().map(UserId::new),
+        priority: RequestPriority::parse(&cell(row, 8)).unwrap_or_default(),
+        due: DateTime::parse_from_rfc3339(cell(row, 9).trim())
+            .ok()
+            .map(|due| due.with_timezone(&Utc)),
+        last_reminder: cell(row, 10).trim().parse().ok(),
+        claims: parse_claims(&cell(row, 11)),
+        crafter: cell(row, 12).trim().parse().ok().map(UserId::new),
+    })
+}
+
+fn request_row_values(row: &RequestRow) -> Vec<Value> {
+    vec![
+        row.request_id.clone().into(),
+        row.product.clone().into(),
+        row.resource.clone().into(),
+        row.amount.to_string().into(),
+        row.status.as_str().into(),
+        row.thread_id.to_string().into(),
+        row.requester
+            .map(|user| user.to_string())
+            .unwrap_or_default()
+            .into(),
+        row.reserved.to_string().into(),
+        row.priority.as_str().into(),
+        row.due
+            .map(|due| due.to_rfc3339())
+            .unwrap_or_default()
+            .into(),
+        row.last_reminder
+            .map(|hours| hours.to_string())
+            .unwrap_or_default()
+            .into(),
+        format_claims(&row.claims).into(),
+        row.crafter
+            .map(|user| user.to_string())
+            .unwrap_or_default()
+            .into(),
+    ]
+}
+
+#[async_trait]
+impl LedgerStore for SheetsStore {
+    async fn inventory(&self) -> Result<Inventory, BotError> {
+        let values = self
+            .read(&self.inventory_sheet_id, &self.inventory_range)
+            .await?;
+        Ok(parse_inventory(&values))
+    }
+
+    async fn adjust_inventory(
+        &self,
+        deltas: &[(String, i64)],
+    ) -> Result<InventoryAdjustment, BotError> {
+        let _guard = self.write_lock.lock().await;
+
+        // Re-read under the lock so the deltas land on the latest stock
+        let mut values = self
+            .read(&self.inventory_sheet_id, &self.inventory_range)
+            .await?;
+        let mut inventory = parse_inventory(&values);
+        let adjustment = apply_deltas(&mut inventory, deltas);
+        let InventoryAdjustment::Applied(updated) = &adjustment else {
+            return Ok(adjustment);
+        };
+
+        // Keep existing rows (and how their names are written) in place...
+        let mut written = Vec::new();
+        for row in values.iter_mut() {
+            let key = normalize_resource_key(&cell(row, 0));
+            if let Some(amount) = updated.get(&key) {
+                *row = vec![row[0].clone(), Value::from(*amount)];
+                written.push(key);
+            }
+        }
+
+        // ...and add anything new at the bottom
+        let mut new_keys: Vec<&String> = updated
+            .keys()
+            .filter(|key| !written.contains(key))
+            .collect();
+        new_keys.sort();
+        for key in new_keys {
+            values.push(vec![key.clone().into(), Value::from(updated[key])]);
+        }
+
+        self.write(&self.inventory_sheet_id, &self.inventory_range, values)
+            .await?;
+        Ok(adjustment)
+    }
+
+    async fn append_ledger(&self, entries: &[LedgerEntry]) -> Result<(), BotError> {
+        let values = entries
+            .iter()
+            .map(|entry| {
+                vec![
+                    entry.resource.clone().into(),
+                    entry.amount.into(),
+                    entry.date.clone().into(),
+                    entry.user.clone().into(),
+                    entry.reason.clone().unwrap_or_default().into(),
+                    entry.txn_id.clone().into(),
+                    entry.reverts.clone().unwrap_or_default().into(),
+                    format_amounts(&entry.allocations).into(),
+                    entry
+                        .user_id
+                        .map(|user| user.to_string())
+                        .unwrap_or_default()
+                        .into(),
+                ]
+            })
+            .collect();
+        self.append(&self.ledger_sheet_id, &self.ledger_range, values)
+            .await
+    }
+
+    async fn ledger_entries(&self) -> Result<Vec<LedgerEntry>, BotError> {
+        let values = self.read(&self.ledger_sheet_id, &self.ledger_range).await?;
+        Ok(values
+            .iter()
+            .filter_map(|row| parse_ledger_row(row))
+            .collect())
+    }
+
+    async fn request_rows(&self) -> Result<Vec<RequestRow>, BotError> {
+        let values = self
+            .read(&self.request_sheet_id, &self.request_range)
+            .await?;
+        Ok(values
+            .iter()
+            .filter_map(|row| parse_request_row(row))
+            .collect())
+    }
+
+    async fn append_request_rows(&self, rows: &[RequestRow]) -> Result<(), BotError> {
+        let values = rows.iter().map(request_row_values).collect();
+        self.append(&self.request_sheet_id, &self.request_range, values)
+            .await
+    }
+
+    async fn update_request_rows(&self, rows: &[RequestRow]) -> Result<(), BotError> {
+        let _guard = self.write_lock.lock().await;
+        let mut values = self
+            .read(&self.request_sheet_id, &self.request_range)
+            .await?;
+        for raw in values.iter_mut() {
+            let id = cell(raw, 0);
+            let resource = cell(raw, 2);
+            if let Some(row) = rows
+                .iter()
+                .find(|row| row.request_id == id && row.resource == resource)
+            {
+                *raw = request_row_values(row);
+            }
+        }
+        self.write(&self.request_sheet_id, &self.request_range, values)
+            .await
+    }
+}
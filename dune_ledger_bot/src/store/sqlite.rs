@@ -0,0 +1,414 @@
+use super::{
+    Inventory, InventoryAdjustment, LedgerEntry, LedgerStore, RequestPriority, RequestRow,
+    RequestStatus, apply_deltas, format_claims, parse_claims,
+};
+use crate::BotError;
+use crate::utils::resources::normalize_resource_key;
+use async_trait::async_trait;
+use poise::serenity_prelude::{ChannelId, UserId};
+use rusqlite::{Connection, params};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+// Applied in order; `PRAGMA user_version` records how many have run
+const MIGRATIONS: &[&str] = &[
+    "
+    CREATE TABLE inventory (
+        resource TEXT PRIMARY KEY,
+        amount   INTEGER NOT NULL DEFAULT 0
+    );
+    CREATE TABLE ledger (
+        id       INTEGER PRIMARY KEY AUTOINCREMENT,
+        resource TEXT NOT NULL,
+        amount   INTEGER NOT NULL,
+        date     TEXT NOT NULL,
+        user     TEXT NOT NULL
+    );
+    CREATE TABLE requests (
+        request_id TEXT PRIMARY KEY,
+        product    TEXT NOT NULL,
+        status     TEXT NOT NULL,
+        thread_id  INTEGER NOT NULL
+    );
+    CREATE TABLE request_lines (
+        id         INTEGER PRIMARY KEY AUTOINCREMENT,
+        request_id TEXT NOT NULL REFERENCES requests (request_id),
+        resource   TEXT NOT NULL,
+        amount     INTEGER NOT NULL
+    );
+    CREATE INDEX request_lines_request ON request_lines (request_id);
+",
+    "
+    ALTER TABLE ledger ADD COLUMN reason TEXT;
+",
+    "
+    ALTER TABLE ledger ADD COLUMN txn_id TEXT NOT NULL DEFAULT '';
+    ALTER TABLE ledger ADD COLUMN reverts TEXT;
+    CREATE INDEX ledger_txn ON ledger (txn_id);
+    CREATE TABLE ledger_allocations (
+        txn_id     TEXT NOT NULL,
+        request_id TEXT NOT NULL,
+        amount     INTEGER NOT NULL
+    );
+",
+    "
+    ALTER TABLE requests ADD COLUMN requester INTEGER;
+",
+    "
+    ALTER TABLE request_lines ADD COLUMN reserved INTEGER NOT NULL DEFAULT 0;
+",
+    "
+    ALTER TABLE requests ADD COLUMN priority TEXT NOT NULL DEFAULT 'normal';
+",
+    "
+    ALTER TABLE requests ADD COLUMN due TEXT;
+    ALTER TABLE requests ADD COLUMN last_reminder INTEGER;
+",
+    "
+    ALTER TABLE requests ADD COLUMN crafter INTEGER;
+    ALTER TABLE request_lines ADD COLUMN claims TEXT NOT NULL DEFAULT '';
+",
+    "
+    ALTER TABLE ledger ADD COLUMN user_id INTEGER;
+",
+];
+
+/// Local single-file store: no Google account needed and every change is a transaction,
+/// so concurrent commands can't overwrite each other's rows
+pub struct SqliteStore {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl SqliteStore {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, BotError> {
+        let mut conn = Connection::open(path)?;
+        conn.pragma_update(None, "foreign_keys", true)?;
+        migrate(&mut conn)?;
+        Ok(SqliteStore {
+            conn: Arc::new(Mutex::new(conn)),
+        })
+    }
+
+    // rusqlite is blocking, so every query runs off the async executor
+    async fn with_conn<T, F>(&self, f: F) -> Result<T, BotError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Connection) -> rusqlite::Result<T> + Send + 'static,
+    {
+        let conn = self.conn.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut conn = conn.lock().map_err(|_| "SQLite connection lock poisoned")?;
+            f(&mut conn).map_err(BotError::from)
+        })
+        .await?
+    }
+}
+
+fn migrate(conn: &mut Connection) -> rusqlite::Result<()> {
+    let applied: usize = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    for (idx, migration) in MIGRATIONS.iter().enumerate().skip(applied) {
+        let tx = conn.transaction()?;
+        tx.execute_batch(migration)?;
+        tx.pragma_update(None, "user_version", idx + 1)?;
+        tx.commit()?;
+    }
+    Ok(())
+}
+
+fn status_from_sql(raw: String) -> rusqlite::Result<RequestStatus> {
+    RequestStatus::parse(&raw).ok_or_else(|| {
+        rusqlite::Error::FromSqlConversionFailure(
+            0,
+            rusqlite::types::Type::Text,
+            format!("unknown request status '{}'", raw).into(),
+        )
+    })
+}
+
+#[async_trait]
+impl LedgerStore for SqliteStore {
+    async fn inventory(&self) -> Result<Inventory, BotError> {
+        self.with_conn(|conn| {
+            let mut stmt = conn.prepare("SELECT resource, amount FROM inventory")?;
+            let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            rows.collect()
+        })
+        .await
+    }
+
+    async fn adjust_inventory(
+        &self,
+        deltas: &[(String, i64)],
+    ) -> Result<InventoryAdjustment, BotError> {
+        let deltas = deltas.to_vec();
+        self.with_conn(move |conn| {
+            let tx = conn.transaction()?;
+            let mut inventory: Inventory = {
+                let mut stmt = tx.prepare("SELECT resource, amount FROM inventory")?;
+                let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+                rows.collect::<rusqlite::Result<_>>()?
+            };
+
+            let adjustment = apply_deltas(&mut inventory, &deltas);
+            if let InventoryAdjustment::Applied(updated) = &adjustment {
+                // Only the resources the deltas touched
+                let mut touched: Vec<String> = deltas
+                    .iter()
+                    .map(|(resource, _)| normalize_resource_key(resource))
+                    .collect();
+                touched.sort();
+                touched.dedup();
+                for resource in &touched {
+                    let amount = updated[resource];
+                    tx.execute(
+                        "INSERT INTO inventory (resource, amount) VALUES (?1, ?2)
+                         ON CONFLICT (resource) DO UPDATE SET amount = excluded.amount",
+                        params![resource, amount],
+                    )?;
+                }
+            }
+            tx.commit()?;
+            Ok(adjustment)
+        })
+        .await
+    }
+
+    async fn append_ledger(&self, entries: &[LedgerEntry]) -> Result<(), BotError> {
+        let entries = entries.to_vec();
+        self.with_conn(move |conn| {
+            let tx = conn.transaction()?;
+            for entry in &entries {
+                tx.execute(
+                    "INSERT INTO ledger
+                        (resource, amount, date, user, reason, txn_id, reverts, user_id)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                    params![
+                        entry.resource,
+                        entry.amount,
+                        entry.date,
+                        entry.user,
+                        entry.reason,
+                        entry.txn_id,
+                        entry.reverts,
+                        entry.user_id.map(|user| user.get() as i64)
+                    ],
+                )?;
+                for (request_id, amount) in &entry.allocations {
+                    tx.execute(
+                        "INSERT INTO ledger_allocations (txn_id, request_id, amount)
+                         VALUES (?1, ?2, ?3)",
+                        params![entry.txn_id, request_id, amount],
+                    )?;
+                }
+            }
+            tx.commit()
+        })
+        .await
+    }
+
+    async fn ledger_entries(&self) -> Result<Vec<LedgerEntry>, BotError> {
+        self.with_conn(|conn| {
+            let mut allocations: HashMap<String, Vec<(String, u64)>> = HashMap::new();
+            let mut stmt = conn.prepare(
+                "SELECT txn_id, request_id, amount FROM ledger_allocations ORDER BY rowid",
+            )?;
+            let rows = stmt.query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get(1)?, row.get(2)?))
+            })?;
+            for row in rows {
+                let (txn_id, request_id, amount) = row?;
+                allocations
+                    .entry(txn_id)
+                    .or_default()
+                    .push((request_id, amount));
+            }
+
+            let mut stmt = conn.prepare(
+                "SELECT resource, amount, date, user, reason, txn_id, reverts, user_id
+                 FROM ledger ORDER BY id",
+            )?;
+            let rows = stmt.query_map([], |row| {
+                let txn_id: String = row.get(5)?;
+                Ok(LedgerEntry {
+                    resource: row.get(0)?,
+                    amount: row.get(1)?,
+                    date: row.get(2)?,
+                    user: row.get(3)?,
+                    user_id: row
+                        .get::<_, Option<i64>>(7)?
+                        .map(|id| UserId::new(id as u64)),
+                    reason: row.get(4)?,
+                    allocations: allocations.get(&txn_id).cloned().unwrap_or_default(),
+                    txn_id,
+                    reverts: row.get(6)?,
+                })
+            })?;
+            rows.collect()
+        })
+        .await
+    }
+
+    async fn request_rows(&self) -> Result<Vec<RequestRow>, BotError> {
+        self.with_conn(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT r.request_id, r.product, l.resource, l.amount, r.status, r.thread_id,
+                        r.requester, l.reserved, r.priority, r.due, r.last_reminder,
+                        r.crafter, l.claims
+                 FROM request_lines l JOIN requests r ON r.request_id = l.request_id
+                 ORDER BY l.id",
+            )?;
+            let rows = stmt.query_map([], |row| {
+                Ok(RequestRow {
+                    request_id: row.get(0)?,
+                    product: row.get(1)?,
+                    resource: row.get(2)?,
+                    amount: row.get(3)?,
+                    reserved: row.get(7)?,
+                    priority: RequestPriority::parse(&row.get::<_, String>(8)?).unwrap_or_default(),
+                    status: status_from_sql(row.get(4)?)?,
+                    thread_id: ChannelId::new(row.get::<_, i64>(5)? as u64),
+                    requester: row
+                        .get::<_, Option<i64>>(6)?
+                        .map(|id| UserId::new(id as u64)),
+                    due: row.get(9)?,
+                    last_reminder: row.get(10)?,
+                    crafter: row
+                        .get::<_, Option<i64>>(11)?
+                        .map(|id| UserId::new(id as u64)),
+                    claims: parse_claims(&row.get::<_, String>(12)?),
+                })
+            })?;
+            rows.collect()
+        })
+        .await
+    }
+
+    async fn append_request_rows(&self, rows: &[RequestRow]) -> Result<(), BotError> {
+        let rows = rows.to_vec();
+        self.with_conn(move |conn| {
+            let tx = conn.transaction()?;
+            for row in &rows {
+                tx.execute(
+                    "INSERT INTO requests
+                        (request_id, product, status, thread_id, requester, priority, due,
+                         last_reminder, crafter)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+                     ON CONFLICT (request_id) DO NOTHING",
+                    params![
+                        row.request_id,
+                        row.product,
+                        row.status.as_str(),
+                        row.thread_id.get() as i64,
+                        row.requester.map(|user| user.get() as i64),
+                        row.priority.as_str(),
+                        row.due,
+                        row.last_reminder,
+                        row.crafter.map(|user| user.get() as i64)
+                    ],
+                )?;
+                tx.execute(
+                    "INSERT INTO request_lines (request_id, resource, amount, reserved, claims)
+                     VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![
+                        row.request_id,
+                        row.resource,
+                        row.amount,
+                        row.reserved,
+                        format_claims(&row.claims)
+                    ],
+                )?;
+            }
+            tx.commit()
+        })
+        .await
+    }
+
+    async fn update_request_rows(&self, rows: &[RequestRow]) -> Result<(), BotError> {
+        let rows = rows.to_vec();
+        self.with_conn(move |conn| {
+            let tx = conn.transaction()?;
+            for row in &rows {
+                tx.execute(
+                    "UPDATE requests
+                     SET product = ?2, status = ?3, thread_id = ?4, priority = ?5, due = ?6,
+                         last_reminder = ?7, crafter = ?8
+                     WHERE request_id = ?1",
+                    params![
+                        row.request_id,
+                        row.product,
+                        row.status.as_str(),
+                        row.thread_id.get() as i64,
+                        row.priority.as_str(),
+                        row.due,
+                        row.last_reminder,
+                        row.crafter.map(|user| user.get() as i64)
+                    ],
+                )?;
+                tx.execute(
+                    "UPDATE request_lines SET amount = ?3, reserved = ?4, claims = ?5
+                     WHERE request_id = ?1 AND resource = ?2",
+                    params![
+                        row.request_id,
+                        row.resource,
+                        row.amount,
+                        row.reserved,
+                        format_claims(&row.claims)
+                    ],
+                )?;
+            }
+            tx.commit()
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn migrations_upgrade_old_databases() {
+        // A database made by the first release, with a row in every table
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(MIGRATIONS[0]).unwrap();
+        conn.pragma_update(None, "user_version", 1).unwrap();
+        conn.execute_batch(
+            "INSERT INTO inventory VALUES ('iron ore', 700);
+             INSERT INTO ledger (resource, amount, date, user)
+                 VALUES ('Iron Ore', 700, '2024-01-01', 'paul');
+             INSERT INTO requests VALUES ('req', 'Stillsuit', 'in_progress', 42);
+             INSERT INTO request_lines (request_id, resource, amount)
+                 VALUES ('req', 'Iron Ore', 1200);",
+        )
+        .unwrap();
+
+        migrate(&mut conn).unwrap();
+        // Already up to date, so nothing runs twice
+        migrate(&mut conn).unwrap();
+        let version: usize = conn
+            .pragma_query_value(None, "user_version", |row| row.get(0))
+            .unwrap();
+        assert_eq!(version, MIGRATIONS.len());
+
+        let store = SqliteStore {
+            conn: Arc::new(Mutex::new(conn)),
+        };
+        assert_eq!(store.inventory().await.unwrap()["iron ore"], 700);
+
+        let entries = store.ledger_entries().await.unwrap();
+        assert_eq!(entries.len(), 1);
+        let legacy = &entries[0];
+        assert_eq!((legacy.user.as_str(), legacy.user_id), ("paul", None));
+        assert!(legacy.txn_id.is_empty() && legacy.reason.is_none());
+        assert!(legacy.reverts.is_none() && legacy.allocations.is_empty());
+
+        let rows = store.request_rows().await.unwrap();
+        assert_eq!(rows.len(), 1);
+        let line = &rows[0];
+        assert_eq!((line.amount, line.reserved), (1200, 0));
+        assert_eq!(line.priority, RequestPriority::Normal);
+        assert!(line.requester.is_none() && line.crafter.is_none() && line.due.is_none());
+        assert!(line.claims.is_empty() && line.last_reminder.is_none());
+    }
+}
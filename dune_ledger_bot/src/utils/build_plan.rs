@@ -0,0 +1,426 @@
+//! Build plans uploaded as files: JSON or CSV exports of a crafting calculator, or
+//! plain text lists. Only the uploaded bytes are read; nothing is fetched.
+
+use crate::BotError;
+use crate::utils::material_list::{parse_material_list, parse_quantity};
+use crate::utils::resources::normalize_resource_key;
+use serde_json::Value;
+
+// Keys that hold the list of materials, a material's name and its amount, in the
+// spellings calculators and spreadsheets commonly use (compared case-insensitively)
+const LIST_KEYS: &[&str] = &[
+    "materials",
+    "resources",
+    "items",
+    "ingredients",
+    "totals",
+    "requirements",
+];
+const NAME_KEYS: &[&str] = &["name", "item", "resource", "material", "ingredient"];
+const AMOUNT_KEYS: &[&str] = &["amount", "quantity", "qty", "count", "total", "needed"];
+// Top-level keys naming what the plan builds
+const TITLE_KEYS: &[&str] = &["product", "title", "name", "item"];
+
+/// Materials decoded from an uploaded plan
+pub struct BuildPlan {
+    /// What the plan builds, when the file says
+    pub title: Option<String>,
+    /// `(amount, name)` lines, names as written in the file
+    pub materials: Vec<(u64, String)>,
+}
+
+/// Decode `data` by its file name (`.json`, `.csv`, `.tsv`, `.txt`), or by its content
+/// when the extension says nothing. A material listed several times (normal for plans
+/// made of several parts) comes back once, with its amounts added up.
+pub fn parse_build_plan(file_name: &str, data: &[u8]) -> Result<BuildPlan, BotError> {
+    let text = std::str::from_utf8(data)
+        .map_err(|_| format!("❌ {} isn't a text file.", file_name))?
+        .trim_start_matches('\u{feff}');
+    let extension = file_name
+        .rsplit_once('.')
+        .map(|(_, ext)| ext.to_lowercase())
+        .unwrap_or_default();
+    let looks_like_json = || {
+        let trimmed = text.trim_start();
+        trimmed.starts_with('{') || trimmed.starts_with('[')
+    };
+
+    let plan = match extension.as_str() {
+        "csv" | "tsv" => from_csv(text).map_err(|e| format!("❌ {}: {}", file_name, e))?,
+        "txt" => from_text(text),
+        "json" => from_json_text(file_name, text)?,
+        _ if looks_like_json() => from_json_text(file_name, text)?,
+        _ => from_text(text),
+    };
+
+    if plan.materials.is_empty() {
+        return Err(format!("❌ Couldn't find any materials in {}.", file_name).into());
+    }
+    Ok(BuildPlan {
+        title: plan.title,
+        materials: merge_materials(plan.materials),
+    })
+}
+
+fn from_text(text: &str) -> BuildPlan {
+    BuildPlan {
+        title: None,
+        materials: parse_material_list(text),
+    }
+}
+
+fn from_json_text(file_name: &str, text: &str) -> Result<BuildPlan, String> {
+    let value: Value = serde_json::from_str(text)
+        .map_err(|e| format!("❌ Can't read {} as JSON: {}", file_name, e))?;
+    from_json(&value).map_err(|e| format!("❌ {}: {}", file_name, e))
+}
+
+// Add up lines naming the same material (ignoring case), keeping the first spelling
+fn merge_materials(materials: Vec<(u64, String)>) -> Vec<(u64, String)> {
+    let mut merged: Vec<(u64, String)> = Vec::new();
+    for (amount, name) in materials {
+        let key = normalize_resource_key(&name);
+        match merged
+            .iter_mut()
+            .find(|(_, existing)| normalize_resource_key(existing) == key)
+        {
+            Some((total, _)) => *total += amount,
+            None => merged.push((amount, name)),
+        }
+    }
+    merged
+}
+
+fn key_in<'a>(object: &'a serde_json::Map<String, Value>, keys: &[&str]) -> Option<&'a Value> {
+    object
+        .iter()
+        .find(|(key, _)| keys.contains(&key.to_lowercase().as_str()))
+        .map(|(_, value)| value)
+}
+
+// A JSON amount: a whole number, or a string like "1,200" or "1.2k"
+fn json_amount(value: &Value) -> Option<u64> {
+    match value {
+        Value::Number(number) => number.as_u64().or_else(|| {
+            number
+                .as_f64()
+                .filter(|amount| *amount >= 0.0 && amount.fract() == 0.0)
+                .map(|amount| amount as u64)
+        }),
+        Value::String(text) => parse_quantity(&text.split_whitespace().collect::<String>()),
+        _ => None,
+    }
+}
+
+fn from_json(value: &Value) -> Result<BuildPlan, String> {
+    let title = match value {
+        Value::Object(object) => match key_in(object, TITLE_KEYS) {
+            Some(Value::String(title)) => Some(title.trim().to_string()),
+            _ => None,
+        },
+        _ => None,
+    };
+    let list = find_list(value).ok_or("no list of materials found")?;
+    Ok(BuildPlan {
+        title,
+        materials: json_materials(list)?,
+    })
+}
+
+// The materials: a top-level array, or the first list key found, searching nested
+// objects breadth-first so `{"plan": {"materials": [...]}}` works too
+fn find_list(value: &Value) -> Option<&Value> {
+    if value.is_array() {
+        return Some(value);
+    }
+    let mut queue = vec![value];
+    while !queue.is_empty() {
+        let mut next = Vec::new();
+        for value in queue {
+            let Value::Object(object) = value else {
+                continue;
+            };
+            if let Some(list) = key_in(object, LIST_KEYS)
+                && (list.is_array() || list.is_object())
+            {
+                return Some(list);
+            }
+            next.extend(object.values().filter(|value| value.is_object()));
+        }
+        queue = next;
+    }
+    // A bare `{"Iron Ore": 1200}` map
+    match value {
+        Value::Object(object)
+            if !object.is_empty() && object.values().all(|v| json_amount(v).is_some()) =>
+        {
+            Some(value)
+        }
+        _ => None,
+    }
+}
+
+fn json_materials(list: &Value) -> Result<Vec<(u64, String)>, String> {
+    let mut materials = Vec::new();
+    match list {
+        Value::Object(map) => {
+            for (name, amount) in map {
+                let amount = json_amount(amount)
+                    .ok_or_else(|| format!("'{}' has no usable amount", name))?;
+                materials.push((amount, name.trim().to_string()));
+            }
+        }
+        Value::Array(entries) => {
+            for (idx, entry) in entries.iter().enumerate() {
+                let line = match entry {
+                    Value::Object(object) => key_in(object, NAME_KEYS)
+                        .and_then(Value::as_str)
+                        .zip(key_in(object, AMOUNT_KEYS).and_then(json_amount))
+                        .map(|(name, amount)| (amount, name.trim().to_string())),
+                    // ["Iron Ore", 1200] or [1200, "Iron Ore"]
+                    Value::Array(pair) if pair.len() == 2 => match (&pair[0], &pair[1]) {
+                        (Value::String(name), amount) | (amount, Value::String(name)) => {
+                            json_amount(amount).map(|amount| (amount, name.trim().to_string()))
+                        }
+                        _ => None,
+                    },
+                    _ => None,
+                };
+                let line =
+                    line.ok_or_else(|| format!("entry {} needs a name and an amount", idx + 1))?;
+                materials.push(line);
+            }
+        }
+        _ => return Err("the materials aren't a list".to_string()),
+    }
+    Ok(materials)
+}
+
+// Split CSV text into rows of cells, honouring quotes ("1,200" stays one cell)
+fn csv_rows(text: &str, delimiter: char) -> Vec<Vec<String>> {
+    let mut rows = Vec::new();
+    for line in text.lines() {
+        let mut cells = Vec::new();
+        let mut cell = String::new();
+        let mut quoted = false;
+        let mut chars = line.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' if quoted && chars.peek() == Some(&'"') => {
+                    cell.push('"');
+                    chars.next();
+                }
+                '"' => quoted = !quoted,
+                c if c == delimiter && !quoted => cells.push(std::mem::take(&mut cell)),
+                c => cell.push(c),
+            }
+        }
+        cells.push(cell);
+        if cells.iter().any(|cell| !cell.trim().is_empty()) {
+            rows.push(
+                cells
+                    .into_iter()
+                    .map(|cell| cell.trim().to_string())
+                    .collect(),
+            );
+        }
+    }
+    rows
+}
+
+fn from_csv(text: &str) -> Result<BuildPlan, String> {
+    // Whichever delimiter the first line uses most (European exports use ';')
+    let first_line = text.lines().next().unwrap_or_default();
+    let delimiter = [',', ';', '\t']
+        .into_iter()
+        .max_by_key(|d| first_line.matches(*d).count())
+        .unwrap_or(',');
+    let rows = csv_rows(text, delimiter);
+    let cell_amount = |cell: &str| parse_quantity(&cell.split_whitespace().collect::<String>());
+
+    let header = rows.first().and_then(|row| {
+        let column = |keys: &[&str]| {
+            row.iter()
+                .position(|cell| keys.contains(&cell.to_lowercase().as_str()))
+        };
+        column(NAME_KEYS).zip(column(AMOUNT_KEYS))
+    });
+
+    let mut materials = Vec::new();
+    match header {
+        Some((name_col, amount_col)) => {
+            for (idx, row) in rows.iter().enumerate().skip(1) {
+                let name = row.get(name_col).map_or("", String::as_str);
+                if name.is_empty() {
+                    continue;
+                }
+                let amount = row
+                    .get(amount_col)
+                    .and_then(|cell| cell_amount(cell))
+                    .ok_or_else(|| format!("row {} ({}) has no usable amount", idx + 1, name))?;
+                materials.push((amount, name.to_string()));
+            }
+        }
+        // No header: the first cell that reads as an amount, named by the first other one
+        None => {
+            for row in &rows {
+                let Some(amount_col) = row.iter().position(|cell| cell_amount(cell).is_some())
+                else {
+                    continue;
+                };
+                let name = row
+                    .iter()
+                    .enumerate()
+                    .find(|&(idx, cell)| idx != amount_col && !cell.is_empty());
+                if let Some((_, name)) = name {
+                    materials.push((cell_amount(&row[amount_col]).unwrap_or(0), name.clone()));
+                }
+            }
+        }
+    }
+    Ok(BuildPlan {
+        title: None,
+        materials,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(file_name: &str, data: &str) -> BuildPlan {
+        parse_build_plan(file_name, data.as_bytes())
+            .unwrap_or_else(|e| panic!("{}: {}", file_name, e))
+    }
+
+    fn error(file_name: &str, data: &str) -> String {
+        match parse_build_plan(file_name, data.as_bytes()) {
+            Ok(plan) => panic!("{} parsed as {:?}", file_name, plan.materials),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    fn items(pairs: &[(u64, &str)]) -> Vec<(u64, String)> {
+        pairs
+            .iter()
+            .map(|&(amount, name)| (amount, name.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn json_list_of_objects_with_title() {
+        let plan = parse(
+            "sandbike.json",
+            r#"{"product": "Sandbike", "materials": [
+                {"name": "Iron Ore", "amount": 1200},
+                {"Item": "Copper Ore", "qty": "1,5k"}
+            ]}"#,
+        );
+        assert_eq!(plan.title.as_deref(), Some("Sandbike"));
+        assert_eq!(
+            plan.materials,
+            items(&[(1200, "Iron Ore"), (1500, "Copper Ore")])
+        );
+    }
+
+    #[test]
+    fn json_map_and_pairs() {
+        assert_eq!(
+            parse("plan.json", r#"{"Iron Ore": 5, "Copper Ore": "1,200"}"#).materials,
+            items(&[(1200, "Copper Ore"), (5, "Iron Ore")])
+        );
+        assert_eq!(
+            parse("plan.json", r#"[["Iron Ore", 5], [7, "Copper Ore"]]"#).materials,
+            items(&[(5, "Iron Ore"), (7, "Copper Ore")])
+        );
+    }
+
+    #[test]
+    fn json_list_nested_in_the_plan() {
+        let plan = parse(
+            "plan.json",
+            r#"{"plan": {"parts": 2, "totals": {"Iron Ore": 40}}}"#,
+        );
+        assert_eq!(plan.title, None);
+        assert_eq!(plan.materials, items(&[(40, "Iron Ore")]));
+    }
+
+    #[test]
+    fn json_without_extension_is_sniffed() {
+        assert_eq!(
+            parse("export", r#"[{"resource": "Iron Ore", "count": 3}]"#).materials,
+            items(&[(3, "Iron Ore")])
+        );
+    }
+
+    #[test]
+    fn repeated_materials_are_added_up() {
+        let plan = parse(
+            "plan.json",
+            r#"{"materials": [
+                {"name": "Iron Ore", "amount": 1000},
+                {"name": "Copper Ore", "amount": 20},
+                {"name": "iron ore", "amount": 300}
+            ]}"#,
+        );
+        assert_eq!(
+            plan.materials,
+            items(&[(1300, "Iron Ore"), (20, "Copper Ore")])
+        );
+    }
+
+    #[test]
+    fn csv_with_header_and_quotes() {
+        let plan = parse(
+            "plan.csv",
+            "Name,Quantity\nIron Ore,\"1,200\"\n\"Plasteel, Plate\",3\n,\n",
+        );
+        assert_eq!(
+            plan.materials,
+            items(&[(1200, "Iron Ore"), (3, "Plasteel, Plate")])
+        );
+    }
+
+    #[test]
+    fn csv_semicolons_and_tabs_without_header() {
+        assert_eq!(
+            parse("plan.csv", "Iron Ore;1.200\nCopper Ore;3\n").materials,
+            items(&[(1200, "Iron Ore"), (3, "Copper Ore")])
+        );
+        assert_eq!(
+            parse("plan.tsv", "Material\tNote\n5\tIron Ore\n").materials,
+            items(&[(5, "Iron Ore")])
+        );
+    }
+
+    #[test]
+    fn extension_wins_over_brackets() {
+        assert_eq!(
+            parse("plan.csv", "[Resource],Amount\nIron Ore,5\n").materials,
+            items(&[(5, "Iron Ore")])
+        );
+        assert_eq!(
+            parse("notes.txt", "[Base] needs:\n- 5 x Iron Ore\n").materials,
+            items(&[(5, "iron ore")])
+        );
+    }
+
+    #[test]
+    fn malformed_input_is_explained() {
+        assert!(error("plan.json", "{\"materials\": [").contains("Can't read plan.json as JSON"));
+        assert!(
+            error("plan.json", r#"{"materials": [{"name": "Iron Ore"}]}"#)
+                .contains("entry 1 needs a name and an amount")
+        );
+        assert!(error("plan.json", r#"{"foo": "bar"}"#).contains("no list of materials"));
+        assert!(
+            error("plan.csv", "Resource,Amount\nIron Ore,lots\n")
+                .contains("row 2 (Iron Ore) has no usable amount")
+        );
+        assert!(error("plan.csv", "Resource,Amount\n").contains("Couldn't find any materials"));
+        assert!(
+            parse_build_plan("plan.json", &[0xff, 0xfe, 0x00])
+                .is_err_and(|e| e.to_string().contains("isn't a text file"))
+        );
+    }
+}
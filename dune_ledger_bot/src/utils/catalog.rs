@@ -0,0 +1,473 @@
+use crate::BotError;
+use crate::utils::files::write_atomic;
+use crate::utils::resources::normalize_resource_key;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError, RwLock, RwLockReadGuard};
+
+// Copied to `catalog_path` the first time the bot starts
+const BUNDLED_CATALOG: &str = include_str!("../../data/catalog.toml");
+
+// Written above the resources whenever `/catalog` saves the file
+const HEADER: &str = "# Resource catalog, kept up to date by `/catalog add|rename|remove|alias`.\n\
+# Hand edits are picked up by `/catalog reload`.\n\n";
+
+/// A resource members can submit, withdraw and request
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Resource {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    /// Other spellings that mean this resource
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+}
+
+/// How a typed name matched the catalog
+#[derive(Clone, Debug, PartialEq)]
+pub enum NameMatch {
+    /// A name or alias, ignoring case
+    Exact(String),
+    /// Close enough to one resource to be taken as it (plural, a typo or two)
+    Corrected(String),
+    /// Nothing close enough; the nearest names, best first, to suggest instead
+    Unknown(Vec<String>),
+}
+
+/// "❌ 'x' is not a recognized resource." plus any suggestions, for replies
+pub fn unknown_resource_message(name: &str, suggestions: &[String]) -> String {
+    let mut message = format!("❌ '{}' is not a recognized resource.", name.trim());
+    match suggestions {
+        [] => message.push_str(" Please choose from the autocompleted options."),
+        [only] => message.push_str(&format!(" Did you mean **{}**?", only)),
+        _ => message.push_str(&format!(
+            " Did you mean one of: {}?",
+            suggestions
+                .iter()
+                .map(|s| format!("**{}**", s))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    }
+    message
+}
+
+/// Short note on an unresolved name for previews: "not in the catalog (did you mean …?)"
+pub fn suggestion_hint(suggestions: &[String]) -> String {
+    if suggestions.is_empty() {
+        "not in the catalog".to_string()
+    } else {
+        format!(
+            "not in the catalog (did you mean {}?)",
+            suggestions.join(" or ")
+        )
+    }
+}
+
+// Similarity (0..1) a name needs to be taken as a resource without asking, how far
+// ahead of the next best it must be, and the least that still gets suggested
+const CORRECT_SIMILARITY: f64 = 0.85;
+const CORRECT_MARGIN: f64 = 0.05;
+const SUGGEST_SIMILARITY: f64 = 0.6;
+const MAX_SUGGESTIONS: usize = 3;
+
+// Lowercased, with every word made singular, so "Iron Ores" and "iron ore" compare equal.
+// Words of three letters or fewer ("gas", "ore") are left alone.
+fn singular_key(name: &str) -> String {
+    normalize_resource_key(name)
+        .split_whitespace()
+        .map(|word| {
+            if word.chars().count() <= 3 {
+                word.to_string()
+            } else if let Some(stem) = word.strip_suffix("ies")
+                && !stem.is_empty()
+            {
+                format!("{}y", stem)
+            } else if let Some(stem) = word.strip_suffix("es")
+                && ["ss", "x", "ch", "sh"]
+                    .iter()
+                    .any(|end| stem.ends_with(end))
+            {
+                stem.to_string()
+            } else if let Some(stem) = word.strip_suffix('s')
+                && !stem.ends_with('s')
+                && !stem.is_empty()
+            {
+                stem.to_string()
+            } else {
+                word.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CatalogFile {
+    #[serde(default)]
+    resource: Vec<Resource>,
+}
+
+/// Every known resource, mirrored to the file at `path`
+pub struct Catalog {
+    path: PathBuf,
+    resources: RwLock<Vec<Resource>>,
+    // Held by whoever is editing and saving the file, so lookups only ever wait for
+    // the finished list to be swapped in, never for the disk
+    saving: Mutex<()>,
+}
+
+fn parse(raw: &str, path: &Path) -> Result<Vec<Resource>, BotError> {
+    let file: CatalogFile = toml::from_str(raw)
+        .map_err(|e| format!("Can't parse catalog {}: {}", path.display(), e))?;
+    check(&file.resource).map_err(|e| format!("Catalog {}: {}", path.display(), e))?;
+    Ok(file.resource)
+}
+
+// Every name and alias has to point at exactly one resource
+fn check(resources: &[Resource]) -> Result<(), String> {
+    let mut seen: Vec<String> = Vec::new();
+    for resource in resources {
+        for name in std::iter::once(&resource.name).chain(&resource.aliases) {
+            let key = normalize_resource_key(name);
+            if key.is_empty() {
+                return Err(format!("'{}' has an empty name or alias", resource.name));
+            }
+            if seen.contains(&key) {
+                return Err(format!("'{}' is listed more than once", name));
+            }
+            seen.push(key);
+        }
+    }
+    Ok(())
+}
+
+impl Catalog {
+    /// Load the catalog at `path`, creating it from the bundled one if it doesn't exist yet
+    pub fn load(path: &Path) -> Result<Self, BotError> {
+        if !path.exists() {
+            std::fs::write(path, BUNDLED_CATALOG)
+                .map_err(|e| format!("Can't create catalog {}: {}", path.display(), e))?;
+        }
+        let raw = std::fs::read_to_string(path)
+            .map_err(|e| format!("Can't read catalog {}: {}", path.display(), e))?;
+        Ok(Catalog {
+            path: path.to_path_buf(),
+            resources: RwLock::new(parse(&raw, path)?),
+            saving: Mutex::new(()),
+        })
+    }
+
+    /// Re-read the file after a hand edit; returns how many resources it now has
+    pub fn reload(&self) -> Result<usize, BotError> {
+        let _saving = self.saving.lock().unwrap_or_else(PoisonError::into_inner);
+        let raw = std::fs::read_to_string(&self.path)
+            .map_err(|e| format!("Can't read catalog {}: {}", self.path.display(), e))?;
+        let resources = parse(&raw, &self.path)?;
+        let count = resources.len();
+        *self
+            .resources
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = resources;
+        Ok(count)
+    }
+
+    fn read(&self) -> RwLockReadGuard<'_, Vec<Resource>> {
+        self.resources
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// The canonical name `name` (or one of its aliases) stands for
+    pub fn resolve(&self, name: &str) -> Option<String> {
+        let key = normalize_resource_key(name);
+        self.read()
+            .iter()
+            .find(|resource| {
+                std::iter::once(&resource.name)
+                    .chain(&resource.aliases)
+                    .any(|candidate| normalize_resource_key(candidate) == key)
+            })
+            .map(|resource| resource.name.clone())
+    }
+
+    /// How to show `name`: its catalog spelling, or `name` itself when it isn't known
+    pub fn display_name(&self, name: &str) -> String {
+        self.resolve(name).unwrap_or_else(|| name.to_string())
+    }
+
+    /// Match free text against every name and alias: exactly, then ignoring plurals,
+    /// then by edit distance. Anything ambiguous is `Unknown`, with suggestions.
+    pub fn find(&self, name: &str) -> NameMatch {
+        if let Some(exact) = self.resolve(name) {
+            return NameMatch::Exact(exact);
+        }
+        let singular = singular_key(name);
+        if singular.is_empty() {
+            return NameMatch::Unknown(Vec::new());
+        }
+
+        // Best similarity per resource, over its name and aliases
+        let mut scored: Vec<(f64, String)> = Vec::new();
+        for resource in self.read().iter() {
+            let mut best: f64 = 0.0;
+            for candidate in std::iter::once(&resource.name).chain(&resource.aliases) {
+                let candidate = singular_key(candidate);
+                if candidate == singular {
+                    return NameMatch::Corrected(resource.name.clone());
+                }
+                let mut score = strsim::normalized_damerau_levenshtein(&singular, &candidate);
+                // "granite" is worth suggesting for "Granite Stone", but not assuming
+                if candidate.contains(&singular) {
+                    score = score.max(SUGGEST_SIMILARITY);
+                }
+                best = best.max(score);
+            }
+            scored.push((best, resource.name.clone()));
+        }
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+        let runner_up = scored.get(1).map_or(0.0, |(score, _)| *score);
+        match scored.first() {
+            Some((score, name))
+                if *score >= CORRECT_SIMILARITY && score - runner_up >= CORRECT_MARGIN =>
+            {
+                NameMatch::Corrected(name.clone())
+            }
+            _ => NameMatch::Unknown(
+                scored
+                    .into_iter()
+                    .take_while(|(score, _)| *score >= SUGGEST_SIMILARITY)
+                    .take(MAX_SUGGESTIONS)
+                    .map(|(_, name)| name)
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Resources whose name, alias or category contains `partial`, sorted by name
+    pub fn search(&self, partial: &str) -> Vec<Resource> {
+        let partial = partial.to_lowercase();
+        let mut found: Vec<Resource> = self
+            .read()
+            .iter()
+            .filter(|resource| {
+                std::iter::once(&resource.name)
+                    .chain(&resource.aliases)
+                    .chain(&resource.category)
+                    .any(|text| text.to_lowercase().contains(&partial))
+            })
+            .cloned()
+            .collect();
+        found.sort_by(|a, b| a.name.cmp(&b.name));
+        found
+    }
+
+    /// Every `(alias, canonical name)` pair
+    pub fn aliases(&self) -> Vec<(String, String)> {
+        self.read()
+            .iter()
+            .flat_map(|resource| {
+                resource
+                    .aliases
+                    .iter()
+                    .map(|alias| (alias.clone(), resource.name.clone()))
+            })
+            .collect()
+    }
+
+    // Apply `edit` to a copy, and only keep it once it checks out and is saved
+    fn update<T>(
+        &self,
+        edit: impl FnOnce(&mut Vec<Resource>) -> Result<T, String>,
+    ) -> Result<T, BotError> {
+        let _saving = self.saving.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut edited = self.read().clone();
+        let result = edit(&mut edited).map_err(|e| format!("❌ {}", e))?;
+        check(&edited).map_err(|e| format!("❌ {}", e))?;
+
+        edited.sort_by(|a, b| a.name.cmp(&b.name));
+        let file = CatalogFile {
+            resource: edited.clone(),
+        };
+        let body = format!("{}{}", HEADER, toml::to_string(&file)?);
+        write_atomic(&self.path, body)?;
+
+        *self
+            .resources
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = edited;
+        Ok(result)
+    }
+
+    pub fn add(&self, name: &str, category: Option<String>) -> Result<(), BotError> {
+        self.update(|resources| {
+            resources.push(Resource {
+                name: name.trim().to_string(),
+                category,
+                aliases: Vec::new(),
+            });
+            Ok(())
+        })
+    }
+
+    /// Give a resource a new name, keeping the old one as an alias; returns the old name
+    pub fn rename(&self, name: &str, new_name: &str) -> Result<String, BotError> {
+        let key = normalize_resource_key(name);
+        let new_key = normalize_resource_key(new_name);
+        self.update(|resources| {
+            let resource = resources
+                .iter_mut()
+                .find(|resource| normalize_resource_key(&resource.name) == key)
+                .ok_or_else(|| format!("'{}' is not in the catalog", name))?;
+            let old_name = std::mem::replace(&mut resource.name, new_name.trim().to_string());
+            // Renaming to one of its own aliases just promotes that alias
+            resource
+                .aliases
+                .retain(|alias| normalize_resource_key(alias) != new_key);
+            resource.aliases.push(old_name.clone());
+            Ok(old_name)
+        })
+    }
+
+    /// Drop a resource and its aliases; returns what was removed
+    pub fn remove(&self, name: &str) -> Result<Resource, BotError> {
+        let key = normalize_resource_key(name);
+        self.update(|resources| {
+            let idx = resources
+                .iter()
+                .position(|resource| normalize_resource_key(&resource.name) == key)
+                .ok_or_else(|| format!("'{}' is not in the catalog", name))?;
+            Ok(resources.remove(idx))
+        })
+    }
+
+    /// Let `alias` stand for `name`; returns the canonical name
+    pub fn alias(&self, name: &str, alias: &str) -> Result<String, BotError> {
+        let key = normalize_resource_key(name);
+        self.update(|resources| {
+            let resource = resources
+                .iter_mut()
+                .find(|resource| normalize_resource_key(&resource.name) == key)
+                .ok_or_else(|| format!("'{}' is not in the catalog", name))?;
+            resource.aliases.push(alias.trim().to_string());
+            Ok(resource.name.clone())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The bundled catalog, so the thresholds are checked against real names
+    fn bundled() -> Catalog {
+        Catalog {
+            path: PathBuf::new(),
+            resources: RwLock::new(parse(BUNDLED_CATALOG, Path::new("catalog.toml")).unwrap()),
+            saving: Mutex::new(()),
+        }
+    }
+
+    fn corrected(name: &str) -> NameMatch {
+        NameMatch::Corrected(name.to_string())
+    }
+
+    #[test]
+    fn singular_keys() {
+        let cases = [
+            ("Iron Ores", "iron ore"),
+            ("Agave Seeds", "agave seed"),
+            ("Corpses", "corpse"),
+            ("Boxes", "box"),
+            ("Glasses", "glass"),
+            ("Batteries", "battery"),
+            ("glass", "glass"),
+            ("gas", "gas"),
+            ("Ore", "ore"),
+        ];
+        for (name, expected) in cases {
+            assert_eq!(singular_key(name), expected, "{}", name);
+        }
+    }
+
+    #[test]
+    fn exact_names_and_aliases_ignore_case() {
+        let catalog = bundled();
+        assert_eq!(
+            catalog.find("iron ORE"),
+            NameMatch::Exact("Iron Ore".to_string())
+        );
+        assert_eq!(
+            catalog.find("Diamodine Blade Parts"),
+            NameMatch::Exact("Diamondine Blade Parts".to_string())
+        );
+    }
+
+    #[test]
+    fn plurals_are_taken_as_the_resource() {
+        let catalog = bundled();
+        assert_eq!(catalog.find("Iron Ores"), corrected("Iron Ore"));
+        assert_eq!(catalog.find("Steel Ingots"), corrected("Steel Ingot"));
+        assert_eq!(catalog.find("Corpses"), corrected("Corpse"));
+        assert_eq!(catalog.find("Blade Part"), corrected("Blade Parts"));
+    }
+
+    #[test]
+    fn small_typos_are_corrected() {
+        let catalog = bundled();
+        assert_eq!(catalog.find("Plastel Plate"), corrected("Plasteel Plate"));
+        assert_eq!(catalog.find("Coper Ore"), corrected("Copper Ore"));
+    }
+
+    #[test]
+    fn typos_match_through_aliases() {
+        let catalog = bundled();
+        catalog
+            .resources
+            .write()
+            .unwrap()
+            .iter_mut()
+            .find(|resource| resource.name == "Spice Melange")
+            .unwrap()
+            .aliases
+            .push("Melange".to_string());
+        assert_eq!(catalog.find("Melanges"), corrected("Spice Melange"));
+        assert_eq!(catalog.find("Melanje"), corrected("Spice Melange"));
+    }
+
+    #[test]
+    fn close_names_are_only_suggested() {
+        let catalog = bundled();
+        assert_eq!(
+            catalog.find("Copper Ing"),
+            NameMatch::Unknown(vec!["Copper Ingot".to_string(), "Copper Ore".to_string()])
+        );
+        assert_eq!(
+            catalog.find("Spice"),
+            NameMatch::Unknown(vec![
+                "Spice Melange".to_string(),
+                "Spice Residue".to_string(),
+                "Spice Sand".to_string(),
+            ])
+        );
+    }
+
+    #[test]
+    fn part_of_a_name_is_suggested_not_accepted() {
+        assert_eq!(
+            bundled().find("granite"),
+            NameMatch::Unknown(vec!["Granite Stone".to_string()])
+        );
+    }
+
+    #[test]
+    fn empty_input_matches_nothing() {
+        let catalog = bundled();
+        assert_eq!(catalog.find(""), NameMatch::Unknown(Vec::new()));
+        assert_eq!(catalog.find("   "), NameMatch::Unknown(Vec::new()));
+        assert_eq!(catalog.find("Mk2 Widget"), NameMatch::Unknown(Vec::new()));
+    }
+}
@@ -0,0 +1,34 @@
+use crate::{BotError, Context};
+
+/// Whether the author holds the configured officer role, or Manage Server when none is set
+/// (or the member lacks the role but runs the server anyway)
+pub async fn author_is_officer(ctx: Context<'_>) -> bool {
+    match ctx.author_member().await {
+        Some(member) => {
+            let has_role = ctx
+                .data()
+                .config
+                .officer_role_id
+                .is_some_and(|role| member.roles.contains(&role));
+            let manages_guild = member.permissions.is_some_and(|p| p.manage_guild());
+            has_role || manages_guild
+        }
+        None => false,
+    }
+}
+
+/// Check for officer-only commands
+pub async fn is_officer(ctx: Context<'_>) -> Result<bool, BotError> {
+    let allowed = author_is_officer(ctx).await;
+
+    // A failed check is only logged by poise, so tell the user ourselves
+    if !allowed {
+        ctx.send(
+            poise::CreateReply::default()
+                .content("❌ Only officers can use this command.")
+                .ephemeral(true),
+        )
+        .await?;
+    }
+    Ok(allowed)
+}
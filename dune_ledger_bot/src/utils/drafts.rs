@@ -0,0 +1,105 @@
+use crate::BotError;
+use crate::utils::files::write_atomic;
+use chrono::{DateTime, Duration, Utc};
+use dashmap::DashMap;
+use once_cell::sync::{Lazy, OnceCell};
+use poise::serenity_prelude::{ChannelId, MessageId, UserId};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+// For storing an ongoing request in the bot's memory
+#[derive(Clone, Serialize, Deserialize)]
+pub struct InProgressRequest {
+    pub author: UserId,
+    /// Where `/request start` was run, so expiry notices land next to the draft
+    pub channel_id: ChannelId,
+    pub product: String,
+    pub resources: Vec<(u64, String)>,
+    /// Crafting tree from `/request expand`, posted in the request's thread
+    #[serde(default)]
+    pub breakdown: Option<String>,
+    pub message_id: MessageId,
+    pub started_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+static IN_FLIGHT: Lazy<DashMap<UserId, InProgressRequest>> = Lazy::new(Default::default);
+
+// Every draft is mirrored to this JSON file so a restart doesn't lose them
+static DRAFTS_PATH: OnceCell<PathBuf> = OnceCell::new();
+static WRITE_LOCK: Mutex<()> = Mutex::new(());
+
+/// Restore drafts saved by a previous run; returns how many came back
+pub fn load(path: &Path) -> Result<usize, BotError> {
+    DRAFTS_PATH
+        .set(path.to_path_buf())
+        .map_err(|_| "Drafts were already loaded")?;
+    if !path.exists() {
+        return Ok(0);
+    }
+
+    let raw = std::fs::read_to_string(path)?;
+    let drafts: Vec<InProgressRequest> = serde_json::from_str(&raw)
+        .map_err(|e| format!("Can't read drafts from {}: {}", path.display(), e))?;
+    let count = drafts.len();
+    for draft in drafts {
+        IN_FLIGHT.insert(draft.author, draft);
+    }
+    Ok(count)
+}
+
+fn persist() -> Result<(), BotError> {
+    let Some(path) = DRAFTS_PATH.get() else {
+        return Ok(());
+    };
+    let _guard = WRITE_LOCK
+        .lock()
+        .map_err(|_| "Drafts write lock poisoned")?;
+
+    let drafts: Vec<InProgressRequest> = IN_FLIGHT.iter().map(|d| d.value().clone()).collect();
+    write_atomic(path, serde_json::to_string_pretty(&drafts)?)?;
+    Ok(())
+}
+
+pub fn get(user: UserId) -> Option<InProgressRequest> {
+    IN_FLIGHT.get(&user).map(|d| d.value().clone())
+}
+
+pub fn contains(user: UserId) -> bool {
+    IN_FLIGHT.contains_key(&user)
+}
+
+/// Store (or replace) the author's draft, marking it as just used
+pub fn save(mut draft: InProgressRequest) -> Result<(), BotError> {
+    draft.updated_at = Utc::now();
+    IN_FLIGHT.insert(draft.author, draft);
+    persist()
+}
+
+pub fn take(user: UserId) -> Result<Option<InProgressRequest>, BotError> {
+    let draft = IN_FLIGHT.remove(&user).map(|(_, d)| d);
+    if draft.is_some() {
+        persist()?;
+    }
+    Ok(draft)
+}
+
+/// Remove and return every draft untouched for longer than `idle`
+pub fn take_expired(idle: Duration) -> Result<Vec<InProgressRequest>, BotError> {
+    let cutoff = Utc::now() - idle;
+    let expired_users: Vec<UserId> = IN_FLIGHT
+        .iter()
+        .filter(|d| d.updated_at < cutoff)
+        .map(|d| *d.key())
+        .collect();
+
+    let expired: Vec<InProgressRequest> = expired_users
+        .into_iter()
+        .filter_map(|user| IN_FLIGHT.remove(&user).map(|(_, d)| d))
+        .collect();
+    if !expired.is_empty() {
+        persist()?;
+    }
+    Ok(expired)
+}
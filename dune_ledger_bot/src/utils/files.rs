@@ -0,0 +1,12 @@
+use std::ffi::OsString;
+use std::path::Path;
+
+/// Replace the file at `path` with `contents`: written next to it first, then renamed
+/// over it, so a crash mid-write can't leave a truncated file behind
+pub fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> std::io::Result<()> {
+    let mut tmp_name = path.file_name().map(OsString::from).unwrap_or_default();
+    tmp_name.push(".tmp");
+    let tmp = path.with_file_name(tmp_name);
+    std::fs::write(&tmp, contents)?;
+    std::fs::rename(&tmp, path)
+}
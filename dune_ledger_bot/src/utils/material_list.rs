@@ -0,0 +1,483 @@
+//! Reads pasted material lists: calculator exports, chat messages, spreadsheet and
+//! markdown tables. Each item is an amount and a name, in either order:
+//!
+//! - `• 1,200 x Iron Ore`, `1200 Iron Ore`, `**1 200** Iron Ore`
+//! - `Iron Ore x 1200`, `Iron Ore: 1.2k`, `Iron Ore (1,200)`
+//! - `| Iron Ore | 1.200 |` or `Iron Ore<TAB>1200`
+//!
+//! Items are separated by new lines, bullets (`•`, `·`), `;` or a comma followed by a
+//! space. Anything without an amount (headers, notes) is skipped.
+
+// Tokens that only join an amount to a name
+const CONNECTORS: &[&str] = &["x", "×", "-", "–", "—", ":", "=", "@"];
+
+// Characters that can group digits in some locale ("1,200", "1.200", "1'200", "1 200")
+const GROUPING: &[char] = &[',', '.', '\'', '’', '_', '\u{a0}', '\u{202f}'];
+
+// Table headers naming the amount column (compared case-insensitively)
+const AMOUNT_HEADERS: &[&str] = &["amount", "qty", "quantity", "count", "total", "needed"];
+
+/// Every `(amount, name)` pair in `input`, in order, names lowercased as written
+pub fn parse_material_list(input: &str) -> Vec<(u64, String)> {
+    let mut items = Vec::new();
+    // The amount column named by the header of the table being read
+    let mut amount_column = None;
+    for line in input.lines() {
+        if line.contains('|') || line.contains('\t') {
+            let cells = table_cells(line);
+            match header_amount_column(&cells) {
+                Some(column) => amount_column = Some(column),
+                None => items.extend(table_row(&cells, amount_column)),
+            }
+            continue;
+        }
+        amount_column = None;
+        for segment in split_items(line) {
+            items.extend(segment_items(segment));
+        }
+    }
+    items
+}
+
+// The cells of a table row, keeping empty ones so columns line up with the header
+fn table_cells(line: &str) -> Vec<String> {
+    let mut cells: Vec<String> = line.split(['|', '\t']).map(clean).collect();
+    // The outer pipes of "| a | b |" don't open or close a cell
+    if line.trim_end().ends_with('|') && cells.last().is_some_and(String::is_empty) {
+        cells.pop();
+    }
+    if line.trim_start().starts_with('|') && cells.first().is_some_and(String::is_empty) {
+        cells.remove(0);
+    }
+    cells
+}
+
+// A header row has no amounts and names one of its columns as the amount
+fn header_amount_column(cells: &[String]) -> Option<usize> {
+    if cells.iter().any(|cell| cell_amount(cell).is_some()) {
+        return None;
+    }
+    cells
+        .iter()
+        .position(|cell| AMOUNT_HEADERS.contains(&cell.to_lowercase().as_str()))
+}
+
+fn cell_amount(cell: &str) -> Option<u64> {
+    let compact: String = cell.split_whitespace().collect();
+    parse_quantity(&compact)
+}
+
+// A table row: the amount is in the header's amount column, or else the rightmost cell
+// holding only an amount (so row numbers and tiers before the name aren't read as it).
+// The name is the first other cell with words.
+fn table_row(cells: &[String], amount_column: Option<usize>) -> Vec<(u64, String)> {
+    if cells.iter().filter(|cell| !cell.is_empty()).count() < 2 {
+        return cells.iter().flat_map(|cell| segment_items(cell)).collect();
+    }
+
+    let amount = match amount_column {
+        Some(column) => cells
+            .get(column)
+            .and_then(|cell| cell_amount(cell))
+            .map(|amount| (column, amount)),
+        None => cells
+            .iter()
+            .enumerate()
+            .rev()
+            .find_map(|(idx, cell)| cell_amount(cell).map(|amount| (idx, amount))),
+    };
+    let Some((amount_idx, amount)) = amount else {
+        return Vec::new();
+    };
+    let name = cells
+        .iter()
+        .enumerate()
+        .filter(|&(idx, cell)| idx != amount_idx && cell_amount(cell).is_none())
+        .map(|(_, cell)| name_from(cell.split_whitespace()))
+        .find(|name| !name.is_empty());
+    match name {
+        Some(name) => vec![(amount, name)],
+        None => Vec::new(),
+    }
+}
+
+// Split a line at bullets, semicolons and list commas (a comma followed by a space;
+// a comma between digits is a thousands separator)
+fn split_items(line: &str) -> Vec<&str> {
+    let mut segments = Vec::new();
+    let mut start = 0;
+    let mut chars = line.char_indices().peekable();
+    while let Some((idx, c)) = chars.next() {
+        let next = chars.peek().map(|&(_, next)| next);
+        let splits = match c {
+            '•' | '·' | ';' => true,
+            ',' => next.is_none_or(char::is_whitespace),
+            _ => false,
+        };
+        if splits {
+            segments.push(&line[start..idx]);
+            start = idx + c.len_utf8();
+        }
+    }
+    segments.push(&line[start..]);
+    segments
+}
+
+// Drop list markers and markdown emphasis so only words and amounts are left
+fn clean(text: &str) -> String {
+    let mut text = text.trim();
+    // "- item", "* item", "+ item", "> item", "1. item", "2) item"
+    for marker in ["- ", "* ", "+ ", "> "] {
+        if let Some(rest) = text.strip_prefix(marker) {
+            text = rest.trim_start();
+        }
+    }
+    let digits = text.chars().take_while(char::is_ascii_digit).count();
+    if digits > 0
+        && let Some(rest) = text[digits..]
+            .strip_prefix(". ")
+            .or_else(|| text[digits..].strip_prefix(") "))
+    {
+        text = rest.trim_start();
+    }
+    text.chars()
+        .map(|c| match c {
+            '*' | '`' | '(' | ')' | '[' | ']' | '"' => ' ',
+            c => c,
+        })
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+fn segment_items(segment: &str) -> Vec<(u64, String)> {
+    let cleaned = clean(segment);
+    let tokens = merge_digit_groups(
+        cleaned
+            .split_whitespace()
+            .map(|token| token.trim_end_matches([':', ',']))
+            .filter(|token| !token.is_empty())
+            .collect(),
+    );
+    let amounts: Vec<Option<u64>> = tokens.iter().map(|token| parse_quantity(token)).collect();
+
+    let mut items = Vec::new();
+    let mut words: Vec<&str> = Vec::new();
+    let mut pending: Option<u64> = None;
+    let first_amount = amounts.iter().position(Option::is_some);
+    let name_first = amounts.last().is_some_and(Option::is_some) && first_amount != Some(0);
+    if let Some(start) = first_amount
+        && !name_first
+    {
+        // "1200 Iron Ore 300 Copper Ore": each amount names the words after it. Words
+        // before the first amount ("need 1,200 x Iron Ore") aren't part of any item.
+        for (token, amount) in tokens.iter().zip(&amounts).skip(start) {
+            match amount {
+                Some(amount) => {
+                    push_item(&mut items, pending.take(), &words);
+                    words.clear();
+                    pending = Some(*amount);
+                }
+                None => words.push(token),
+            }
+        }
+        push_item(&mut items, pending, &words);
+    } else if name_first {
+        // "Iron Ore x 1200 Copper Ore x 300": each amount names the words before it
+        for (token, amount) in tokens.iter().zip(&amounts) {
+            match amount {
+                Some(amount) => {
+                    push_item(&mut items, Some(*amount), &words);
+                    words.clear();
+                }
+                None => words.push(token),
+            }
+        }
+    }
+    items
+}
+
+fn push_item(items: &mut Vec<(u64, String)>, amount: Option<u64>, words: &[&str]) {
+    let name = name_from(words.iter().copied());
+    if let Some(amount) = amount
+        && !name.is_empty()
+    {
+        items.push((amount, name));
+    }
+}
+
+// Words of a name, lowercased, without connectors around it
+fn name_from<'a>(words: impl Iterator<Item = &'a str>) -> String {
+    let words: Vec<&str> = words
+        .map(|word| word.trim_end_matches([':', ',']))
+        .filter(|word| !word.is_empty() && !CONNECTORS.contains(&word.to_lowercase().as_str()))
+        .collect();
+    words.join(" ").to_lowercase()
+}
+
+// "1 200 000" is one amount when the later groups have exactly three digits
+fn merge_digit_groups(tokens: Vec<&str>) -> Vec<String> {
+    let mut merged: Vec<(String, bool)> = Vec::new();
+    for token in tokens {
+        let is_group = token.len() == 3 && token.chars().all(|c| c.is_ascii_digit());
+        if is_group
+            && let Some((last, joined)) = merged.last_mut()
+            && last.chars().all(|c| c.is_ascii_digit())
+            && (*joined || last.len() <= 3)
+        {
+            last.push_str(token);
+            *joined = true;
+            continue;
+        }
+        merged.push((token.to_string(), false));
+    }
+    merged.into_iter().map(|(token, _)| token).collect()
+}
+
+/// Read an amount like `1,200`, `1.200`, `1'200`, `1200x`, `x1200`, `1.2k`, `2,5k` or
+/// `3m`. A lone separator followed by exactly three digits groups thousands (so `1,200k`
+/// is 1.2 million); otherwise it marks decimals, which need a `k`/`m` suffix to count.
+pub fn parse_quantity(token: &str) -> Option<u64> {
+    let lower = token.to_lowercase();
+    let body = lower
+        .trim_start_matches(['x', '×'])
+        .trim_end_matches(['x', '×']);
+    let (body, multiplier) = match body.chars().last() {
+        Some('k') => (&body[..body.len() - 1], 1_000),
+        Some('m') => (&body[..body.len() - 1], 1_000_000),
+        _ => (body, 1),
+    };
+    if !body.starts_with(|c: char| c.is_ascii_digit())
+        || !body.ends_with(|c: char| c.is_ascii_digit())
+        || !body
+            .chars()
+            .all(|c| c.is_ascii_digit() || GROUPING.contains(&c))
+    {
+        return None;
+    }
+
+    let decimal = decimal_separator(body);
+    let mut whole: u64 = 0;
+    let mut fraction = String::new();
+    let mut in_fraction = false;
+    for c in body.chars() {
+        if Some(c) == decimal {
+            in_fraction = true;
+        } else if let Some(digit) = c.to_digit(10) {
+            if in_fraction {
+                fraction.push(c);
+            } else {
+                whole = whole.checked_mul(10)?.checked_add(u64::from(digit))?;
+            }
+        }
+    }
+
+    let mut amount = whole.checked_mul(multiplier)?;
+    if !fraction.is_empty() {
+        let scale = 10u64.checked_pow(fraction.len() as u32)?;
+        let parts = fraction.parse::<u64>().ok()?.checked_mul(multiplier)?;
+        // 1.5 of something isn't an amount; 1.2345k rounds to the nearest unit
+        if multiplier == 1 && parts != 0 {
+            return None;
+        }
+        amount = amount.checked_add((parts + scale / 2) / scale)?;
+    }
+    Some(amount)
+}
+
+// Which of '.' and ',' marks decimals in `body`, if either does
+fn decimal_separator(body: &str) -> Option<char> {
+    let last_dot = body.rfind('.');
+    let last_comma = body.rfind(',');
+    let candidate = match (last_dot, last_comma) {
+        (Some(dot), Some(comma)) => {
+            // Both used: the later one is the decimal mark ("1,234.5", "1.234,5")
+            return Some(if dot > comma { '.' } else { ',' });
+        }
+        (Some(_), None) => '.',
+        (None, Some(_)) => ',',
+        (None, None) => return None,
+    };
+    if body.matches(candidate).count() > 1 {
+        return None;
+    }
+    let digits_after = body
+        .rsplit(candidate)
+        .next()
+        .map_or(0, |rest| rest.chars().filter(char::is_ascii_digit).count());
+    (digits_after != 3).then_some(candidate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Hand-written lists in each supported format (see the fixtures' README)
+    fn fixture(name: &str) -> String {
+        let path = format!(
+            "{}/tests/fixtures/material_lists/{}",
+            env!("CARGO_MANIFEST_DIR"),
+            name
+        );
+        std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("{}: {}", path, e))
+    }
+
+    fn items(pairs: &[(u64, &str)]) -> Vec<(u64, String)> {
+        pairs
+            .iter()
+            .map(|&(amount, name)| (amount, name.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn calculator_bullets() {
+        assert_eq!(
+            parse_material_list(&fixture("synthetic_calculator_bullets.txt")),
+            items(&[
+                (1200, "iron ore"),
+                (300, "copper ore"),
+                (45, "spice-infused iron dust"),
+                (90_000, "water"),
+            ])
+        );
+    }
+
+    #[test]
+    fn name_before_amount() {
+        assert_eq!(
+            parse_material_list(&fixture("synthetic_name_first.txt")),
+            items(&[
+                (1200, "iron ore"),
+                (300, "copper ore"),
+                (1200, "basalt stone"),
+                (2500, "plant fiber"),
+                (1250, "stravidium mass"),
+                (3_000_000, "salvaged metal"),
+            ])
+        );
+    }
+
+    #[test]
+    fn markdown_table() {
+        assert_eq!(
+            parse_material_list(&fixture("synthetic_markdown_table.txt")),
+            items(&[
+                (1200, "iron ore"),
+                (300, "copper ore"),
+                (12_500, "carbon ore")
+            ])
+        );
+    }
+
+    #[test]
+    fn spreadsheet_with_german_separators() {
+        assert_eq!(
+            parse_material_list(&fixture("synthetic_spreadsheet_de.tsv")),
+            items(&[
+                (1200, "iron ore"),
+                (12_500, "carbon ore"),
+                (1500, "jasmium crystal"),
+                (2400, "granite stone"),
+            ])
+        );
+    }
+
+    #[test]
+    fn markdown_bullets_and_emphasis() {
+        assert_eq!(
+            parse_material_list(&fixture("synthetic_markdown_bullets.txt")),
+            items(&[
+                (1200, "iron ore"),
+                (300, "copper ore"),
+                (4500, "granite stone"),
+                (1250, "flour sand"),
+                (20, "fuel cell"),
+                (15, "mechanical parts"),
+            ])
+        );
+    }
+
+    #[test]
+    fn several_items_on_one_line() {
+        assert_eq!(
+            parse_material_list(&fixture("synthetic_chat_one_line.txt")),
+            items(&[
+                (1200, "iron ore"),
+                (300, "copper ore"),
+                (2000, "basalt stone"),
+                (500, "plant fiber"),
+                (1_234_500, "spice sand"),
+            ])
+        );
+    }
+
+    #[test]
+    fn old_space_separated_runs() {
+        assert_eq!(
+            parse_material_list(&fixture("synthetic_legacy_run.txt")),
+            items(&[(1200, "iron ore"), (300, "copper ore"), (45_000, "water")])
+        );
+    }
+
+    #[test]
+    fn numbered_table_rows() {
+        let table = "| # | Resource | Amount |\n|---|---|---|\n| 1 | Iron Ore | 1200 |\n| 2 | Copper Ore | 300 |";
+        assert_eq!(
+            parse_material_list(table),
+            items(&[(1200, "iron ore"), (300, "copper ore")])
+        );
+        // Without a header the rightmost amount is the amount
+        assert_eq!(
+            parse_material_list("| 1 | Iron Ore | 1200 |\n2\tCopper Ore\t300"),
+            items(&[(1200, "iron ore"), (300, "copper ore")])
+        );
+    }
+
+    #[test]
+    fn header_names_the_amount_column() {
+        let table = "Qty\tTier\tResource\n1200\t2\tIron Ore\n300\t1\tCopper Ore";
+        assert_eq!(
+            parse_material_list(table),
+            items(&[(1200, "iron ore"), (300, "copper ore")])
+        );
+    }
+
+    #[test]
+    fn lines_without_amounts_are_skipped() {
+        assert!(parse_material_list("Materials\n\n| Resource | Amount |\n|---|---|").is_empty());
+    }
+
+    #[test]
+    fn quantities() {
+        let cases = [
+            ("1200", Some(1200)),
+            ("1,200", Some(1200)),
+            ("1.200", Some(1200)),
+            ("1'200", Some(1200)),
+            ("1’200", Some(1200)),
+            ("1.234.567", Some(1_234_567)),
+            ("1,234,567", Some(1_234_567)),
+            ("1.2k", Some(1200)),
+            ("2,5k", Some(2500)),
+            ("1.2345k", Some(1235)),
+            ("1,200k", Some(1_200_000)),
+            ("1,234.5k", Some(1_234_500)),
+            ("1.234,5k", Some(1_234_500)),
+            ("3M", Some(3_000_000)),
+            ("x20", Some(20)),
+            ("20x", Some(20)),
+            ("×7", Some(7)),
+            ("1.0", Some(1)),
+            ("1.5", None),
+            ("1,234.5", None),
+            ("k", None),
+            ("x", None),
+            ("Mk2", None),
+            ("12a", None),
+            ("99999999999999999999", None),
+        ];
+        for (token, expected) in cases {
+            assert_eq!(parse_quantity(token), expected, "{}", token);
+        }
+    }
+}
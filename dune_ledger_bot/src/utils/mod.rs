@@ -1 +1,8 @@
-pub mod sheets;
+pub mod build_plan;
+pub mod catalog;
+pub mod checks;
+pub mod drafts;
+pub mod files;
+pub mod material_list;
+pub mod recipes;
+pub mod resources;
@@ -0,0 +1,388 @@
+use crate::BotError;
+use crate::store::Inventory;
+use crate::utils::resources::normalize_resource_key;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::path::Path;
+
+// Shipped with the bot, used unless `recipes_path` points elsewhere
+const BUNDLED_RECIPES: &str = include_str!("../../data/recipes.toml");
+
+/// What one craft of `product` takes and yields
+#[derive(Clone, Debug, Deserialize)]
+pub struct Recipe {
+    pub product: String,
+    #[serde(default = "one")]
+    pub makes: u64,
+    pub ingredients: BTreeMap<String, u64>,
+}
+
+fn one() -> u64 {
+    1
+}
+
+impl Recipe {
+    /// `(amount, ingredient)` lines for at least `quantity` of the product, in whole crafts
+    pub fn materials_for(&self, quantity: u64) -> Result<Vec<(u64, String)>, BotError> {
+        let crafts = quantity.div_ceil(self.makes);
+        self.ingredients
+            .iter()
+            .map(|(name, amount)| {
+                Ok((self.ingredient_amount(*amount, crafts, name)?, name.clone()))
+            })
+            .collect()
+    }
+
+    // `amount` of `ingredient` per craft, times `crafts`, unless that's too many to count
+    fn ingredient_amount(&self, amount: u64, crafts: u64, ingredient: &str) -> Result<u64, String> {
+        amount.checked_mul(crafts).ok_or_else(|| {
+            format!(
+                "❌ {} crafts of {} need more {} than can be counted.",
+                crafts, self.product, ingredient
+            )
+        })
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RecipeFile {
+    #[serde(default)]
+    recipe: Vec<Recipe>,
+}
+
+/// Every known recipe, keyed by normalized product name
+pub struct RecipeBook {
+    recipes: HashMap<String, Recipe>,
+}
+
+impl RecipeBook {
+    /// Load the catalog at `path`, or the bundled one when none is configured
+    pub fn load(path: Option<&Path>) -> Result<Self, BotError> {
+        let raw = match path {
+            Some(path) => std::fs::read_to_string(path)
+                .map_err(|e| format!("Can't read recipes {}: {}", path.display(), e))?,
+            None => BUNDLED_RECIPES.to_string(),
+        };
+        let source = path.map_or("bundled".to_string(), |p| p.display().to_string());
+        Self::parse(&raw, &source)
+    }
+
+    fn parse(raw: &str, source: &str) -> Result<Self, BotError> {
+        let file: RecipeFile =
+            toml::from_str(raw).map_err(|e| format!("Can't parse recipes ({}): {}", source, e))?;
+
+        let mut recipes = HashMap::new();
+        for recipe in file.recipe {
+            if recipe.makes == 0 || recipe.ingredients.is_empty() {
+                return Err(format!(
+                    "Recipe for '{}' ({}) must make at least one item from some ingredients",
+                    recipe.product, source
+                )
+                .into());
+            }
+            let key = normalize_resource_key(&recipe.product);
+            if recipes.insert(key, recipe.clone()).is_some() {
+                return Err(format!(
+                    "Recipe for '{}' ({}) is listed twice",
+                    recipe.product, source
+                )
+                .into());
+            }
+        }
+        Ok(RecipeBook { recipes })
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Recipe> {
+        self.recipes.values()
+    }
+
+    pub fn get(&self, product: &str) -> Option<&Recipe> {
+        self.recipes.get(&normalize_resource_key(product))
+    }
+
+    /// Product names containing `partial`, sorted, for autocomplete
+    pub fn search(&self, partial: &str) -> Vec<&str> {
+        let partial = partial.to_lowercase();
+        let mut names: Vec<&str> = self
+            .recipes
+            .iter()
+            .filter(|(key, _)| key.contains(&partial))
+            .map(|(_, recipe)| recipe.product.as_str())
+            .collect();
+        names.sort();
+        names
+    }
+}
+
+/// A request broken down to what has to be gathered
+pub struct Breakdown {
+    /// Intermediates taken from stock, then raw materials, merged by resource
+    pub lines: Vec<(u64, String)>,
+    /// Indented outline of the crafting tree, one node per line
+    pub tree: String,
+}
+
+impl RecipeBook {
+    /// Expand every line with a recipe down to raw materials. Intermediates already in
+    /// `inventory` are used first (each unit only once), and only the rest is crafted.
+    pub fn expand(
+        &self,
+        lines: &[(u64, String)],
+        inventory: &Inventory,
+    ) -> Result<Breakdown, BotError> {
+        let mut expansion = Expansion {
+            book: self,
+            stock: inventory.clone(),
+            lines: Vec::new(),
+            tree: String::new(),
+            path: Vec::new(),
+        };
+        for (amount, name) in lines {
+            expansion.expand(*amount, name, 0)?;
+        }
+        Ok(Breakdown {
+            lines: expansion.lines,
+            tree: expansion.tree,
+        })
+    }
+}
+
+struct Expansion<'a> {
+    book: &'a RecipeBook,
+    stock: Inventory,
+    lines: Vec<(u64, String)>,
+    tree: String,
+    // Products being expanded above this one, so a recipe loop can't recurse forever
+    path: Vec<String>,
+}
+
+impl Expansion<'_> {
+    fn expand(&mut self, needed: u64, name: &str, depth: usize) -> Result<(), String> {
+        let key = normalize_resource_key(name);
+        let indent = "  ".repeat(depth);
+        let recipe = match self.book.get(name) {
+            Some(recipe) if !self.path.contains(&key) => recipe,
+            // Raw materials are gathered in full; Update compares them with stock later
+            _ => {
+                let _ = writeln!(self.tree, "{}• {} x {}", indent, needed, name);
+                self.add_line(needed, name);
+                return Ok(());
+            }
+        };
+
+        let stock = self.stock.entry(key.clone()).or_insert(0);
+        let from_stock = needed.min(*stock);
+        *stock -= from_stock;
+        let to_craft = needed - from_stock;
+        let crafts = to_craft.div_ceil(recipe.makes);
+
+        let mut note = Vec::new();
+        if from_stock > 0 {
+            note.push(format!("{} in stock", from_stock));
+        }
+        if crafts > 0 && recipe.makes > 1 {
+            note.push(format!("{} crafts of {}", crafts, recipe.makes));
+        }
+        let note = if note.is_empty() {
+            String::new()
+        } else {
+            format!(" ({})", note.join(", "))
+        };
+        let _ = writeln!(
+            self.tree,
+            "{}• {} x {}{}",
+            indent, needed, recipe.product, note
+        );
+
+        if from_stock > 0 {
+            self.add_line(from_stock, &recipe.product);
+        }
+        if crafts == 0 {
+            return Ok(());
+        }
+        self.path.push(key);
+        for (ingredient, amount) in &recipe.ingredients {
+            let amount = recipe.ingredient_amount(*amount, crafts, ingredient)?;
+            self.expand(amount, ingredient, depth + 1)?;
+        }
+        self.path.pop();
+        Ok(())
+    }
+
+    fn add_line(&mut self, amount: u64, name: &str) {
+        let key = normalize_resource_key(name);
+        match self
+            .lines
+            .iter_mut()
+            .find(|(_, existing)| normalize_resource_key(existing) == key)
+        {
+            Some((total, _)) => *total = total.saturating_add(amount),
+            None => self.lines.push((amount, name.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RECIPES: &str = r#"
+[[recipe]]
+product = "Iron Ingot"
+ingredients = { "Iron Ore" = 5 }
+
+[[recipe]]
+product = "Steel Ingot"
+ingredients = { "Iron Ingot" = 1, "Carbon Ore" = 4 }
+
+[[recipe]]
+product = "Silicone Block"
+makes = 2
+ingredients = { "Plant Fiber" = 2, "Flour Sand" = 4 }
+
+[[recipe]]
+product = "Armor Plating"
+ingredients = { "Steel Ingot" = 3, "Silicone Block" = 1 }
+"#;
+
+    fn book() -> RecipeBook {
+        RecipeBook::parse(RECIPES, "test").unwrap()
+    }
+
+    fn lines(pairs: &[(u64, &str)]) -> Vec<(u64, String)> {
+        pairs
+            .iter()
+            .map(|&(amount, name)| (amount, name.to_string()))
+            .collect()
+    }
+
+    fn stock(pairs: &[(&str, u64)]) -> Inventory {
+        pairs
+            .iter()
+            .map(|&(name, amount)| (normalize_resource_key(name), amount))
+            .collect()
+    }
+
+    #[test]
+    fn materials_round_up_to_whole_crafts() {
+        let recipe = book().get("silicone block").unwrap().clone();
+        assert_eq!(
+            recipe.materials_for(2).unwrap(),
+            lines(&[(4, "Flour Sand"), (2, "Plant Fiber")])
+        );
+        assert_eq!(
+            recipe.materials_for(3).unwrap(),
+            lines(&[(8, "Flour Sand"), (4, "Plant Fiber")])
+        );
+    }
+
+    #[test]
+    fn expands_every_level_to_raw_materials() {
+        let breakdown = book()
+            .expand(&lines(&[(2, "Armor Plating")]), &Inventory::new())
+            .unwrap();
+        // 2 plating = 2 silicone (1 craft) and 6 steel (6 iron ingots = 30 ore, 24 carbon)
+        assert_eq!(
+            breakdown.lines,
+            lines(&[
+                (4, "Flour Sand"),
+                (2, "Plant Fiber"),
+                (24, "Carbon Ore"),
+                (30, "Iron Ore")
+            ])
+        );
+        assert_eq!(
+            breakdown.tree.lines().collect::<Vec<_>>(),
+            [
+                "• 2 x Armor Plating",
+                "  • 2 x Silicone Block (1 crafts of 2)",
+                "    • 4 x Flour Sand",
+                "    • 2 x Plant Fiber",
+                "  • 6 x Steel Ingot",
+                "    • 24 x Carbon Ore",
+                "    • 6 x Iron Ingot",
+                "      • 30 x Iron Ore",
+            ]
+        );
+    }
+
+    #[test]
+    fn intermediates_in_stock_are_used_first() {
+        let book = book();
+        let partial = book
+            .expand(
+                &lines(&[(6, "Steel Ingot")]),
+                &stock(&[("Steel Ingot", 2), ("Iron Ingot", 1)]),
+            )
+            .unwrap();
+        // 2 steel from stock; 4 crafted from 1 iron ingot in stock, 3 made from ore
+        assert_eq!(
+            partial.lines,
+            lines(&[
+                (2, "Steel Ingot"),
+                (16, "Carbon Ore"),
+                (1, "Iron Ingot"),
+                (15, "Iron Ore")
+            ])
+        );
+
+        let full = book
+            .expand(
+                &lines(&[(6, "Steel Ingot")]),
+                &stock(&[("Steel Ingot", 10)]),
+            )
+            .unwrap();
+        assert_eq!(full.lines, lines(&[(6, "Steel Ingot")]));
+    }
+
+    #[test]
+    fn stock_is_only_used_once() {
+        let breakdown = book()
+            .expand(
+                &lines(&[(1, "Steel Ingot"), (1, "Steel Ingot")]),
+                &stock(&[("Steel Ingot", 1)]),
+            )
+            .unwrap();
+        assert_eq!(
+            breakdown.lines,
+            lines(&[(1, "Steel Ingot"), (4, "Carbon Ore"), (5, "Iron Ore")])
+        );
+    }
+
+    #[test]
+    fn recipe_loops_stop_at_the_repeated_product() {
+        let book = RecipeBook::parse(
+            r#"
+[[recipe]]
+product = "Spice Sand"
+ingredients = { "Spice Residue" = 2 }
+
+[[recipe]]
+product = "Spice Residue"
+ingredients = { "Spice Sand" = 3 }
+"#,
+            "test",
+        )
+        .unwrap();
+        let breakdown = book
+            .expand(&lines(&[(1, "Spice Sand")]), &Inventory::new())
+            .unwrap();
+        assert_eq!(breakdown.lines, lines(&[(6, "Spice Sand")]));
+    }
+
+    #[test]
+    fn amounts_too_large_to_count_are_an_error() {
+        let book = book();
+        let recipe = book.get("iron ingot").unwrap();
+        assert!(recipe.materials_for(u64::MAX).is_err());
+        assert!(
+            book.expand(
+                &lines(&[(u64::MAX / 2, "Armor Plating")]),
+                &Inventory::new()
+            )
+            .is_err()
+        );
+    }
+}
@@ -0,0 +1,237 @@
+use crate::utils::catalog::Catalog;
+use crate::utils::material_list::parse_material_list;
+use serde::Deserialize;
+
+pub fn normalize_resource_key(s: &str) -> String {
+    s.trim_matches('"')
+        .replace('\u{00a0}', " ")
+        .to_lowercase()
+        .trim()
+        .to_string()
+}
+
+/// One entry of a pasted resource list
+pub struct ListedResource {
+    pub amount: u64,
+    /// As written in the list, lowercased (or the resource it was converted to)
+    pub name: String,
+    /// What was actually written, when it was converted to another resource
+    pub converted_from: Option<(u64, String)>,
+}
+
+impl ListedResource {
+    /// Preview line for the user, showing any conversion
+    pub fn describe(&self) -> String {
+        match &self.converted_from {
+            Some((amount, name)) => format!(
+                "Converted: {} x {} → {} x {}",
+                amount, name, self.amount, self.name
+            ),
+            None => format!("{} x {}", self.amount, self.name),
+        }
+    }
+}
+
+/// How a conversion rounds amounts that don't come out whole
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Rounding {
+    #[default]
+    Down,
+    Nearest,
+    Up,
+}
+
+/// `amount` of `from` counts as `counts_as` of `to`
+pub struct Conversion {
+    pub from: String,
+    pub to: String,
+    pub amount: u64,
+    pub counts_as: u64,
+    pub rounding: Rounding,
+}
+
+impl Conversion {
+    /// How much of `to` `amount` of `from` counts as
+    pub fn apply(&self, amount: u64) -> u64 {
+        let scaled = u128::from(amount) * u128::from(self.counts_as);
+        let per = u128::from(self.amount);
+        let converted = match self.rounding {
+            Rounding::Down => scaled / per,
+            Rounding::Nearest => (scaled + per / 2) / per,
+            Rounding::Up => scaled.div_ceil(per),
+        };
+        u64::try_from(converted).unwrap_or(u64::MAX)
+    }
+
+    /// The least amount of `from` that counts as at least `wanted` of `to`
+    pub fn needed_for(&self, wanted: u64) -> u64 {
+        if wanted == 0 {
+            return 0;
+        }
+        let (wanted, per) = (u128::from(wanted), u128::from(self.amount));
+        let counts_as = u128::from(self.counts_as);
+        let needed = match self.rounding {
+            Rounding::Down => (wanted * per).div_ceil(counts_as),
+            Rounding::Nearest => (wanted * per - per / 2).div_ceil(counts_as),
+            Rounding::Up => (wanted - 1) * per / counts_as + 1,
+        };
+        u64::try_from(needed).unwrap_or(u64::MAX)
+    }
+}
+
+/// Resources that count as another one when listed or submitted, from the config
+pub struct Conversions {
+    rules: Vec<Conversion>,
+}
+
+impl Conversions {
+    pub fn new(rules: Vec<Conversion>) -> Self {
+        Conversions { rules }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Conversion> {
+        self.rules.iter()
+    }
+
+    /// The conversion for `name`, as written or as the catalog resource it stands for
+    pub fn find(&self, name: &str, catalog: &Catalog) -> Option<&Conversion> {
+        let key = normalize_resource_key(name);
+        let resolved = catalog
+            .resolve(name)
+            .map(|name| normalize_resource_key(&name));
+        self.rules.iter().find(|rule| {
+            let from = normalize_resource_key(&rule.from);
+            from == key || Some(&from) == resolved.as_ref()
+        })
+    }
+
+    /// Conversions that produce `name`
+    pub fn producing(&self, name: &str) -> impl Iterator<Item = &Conversion> {
+        let key = normalize_resource_key(name);
+        self.rules
+            .iter()
+            .filter(move |rule| normalize_resource_key(&rule.to) == key)
+    }
+
+    /// `amount` of `name` as what it counts as, when a conversion applies
+    pub fn convert(&self, amount: u64, name: &str, catalog: &Catalog) -> Option<(u64, String)> {
+        self.find(name, catalog)
+            .map(|rule| (rule.apply(amount), rule.to.clone()))
+    }
+}
+
+//* Expects raw resource list pasted from crafting calc → https://dune.geno.gg/calculator/
+/// Every item of a pasted list (see `material_list` for what it understands), in order.
+pub fn parse_resource_list(
+    input: &str,
+    conversions: &Conversions,
+    catalog: &Catalog,
+) -> Vec<ListedResource> {
+    listed_resources(parse_material_list(input), conversions, catalog)
+}
+
+/// `(amount, name)` pairs from a list or an uploaded plan, names lowercased. Resources
+/// with a conversion are listed as what they count as (which can come out as 0);
+/// callers drop what they can't use.
+pub fn listed_resources(
+    items: Vec<(u64, String)>,
+    conversions: &Conversions,
+    catalog: &Catalog,
+) -> Vec<ListedResource> {
+    items
+        .into_iter()
+        .map(|(amount, name)| {
+            let name = name.to_lowercase();
+            match conversions.convert(amount, &name, catalog) {
+                Some((converted, to)) => ListedResource {
+                    amount: converted,
+                    name: to,
+                    converted_from: Some((amount, name)),
+                },
+                None => ListedResource {
+                    amount,
+                    name,
+                    converted_from: None,
+                },
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn conversion(amount: u64, counts_as: u64, rounding: Rounding) -> Conversion {
+        Conversion {
+            from: "Water".to_string(),
+            to: "Corpse".to_string(),
+            amount,
+            counts_as,
+            rounding,
+        }
+    }
+
+    #[test]
+    fn rounding_down() {
+        let water = conversion(45_000, 1, Rounding::Down);
+        assert_eq!(water.apply(90_000), 2);
+        assert_eq!(water.apply(134_999), 2);
+        assert_eq!(water.apply(44_999), 0);
+        assert_eq!(water.needed_for(2), 90_000);
+        assert_eq!(water.needed_for(3), 135_000);
+    }
+
+    #[test]
+    fn rounding_to_nearest() {
+        let sand = conversion(10, 1, Rounding::Nearest);
+        assert_eq!(sand.apply(20), 2);
+        assert_eq!(sand.apply(24), 2);
+        assert_eq!(sand.apply(25), 3);
+        assert_eq!(sand.apply(4), 0);
+        assert_eq!(sand.needed_for(2), 15);
+        assert_eq!(sand.needed_for(3), 25);
+    }
+
+    #[test]
+    fn rounding_up() {
+        let sand = conversion(10, 1, Rounding::Up);
+        assert_eq!(sand.apply(20), 2);
+        assert_eq!(sand.apply(21), 3);
+        assert_eq!(sand.apply(1), 1);
+        assert_eq!(sand.needed_for(2), 11);
+        assert_eq!(sand.needed_for(3), 21);
+    }
+
+    #[test]
+    fn counting_as_several() {
+        let bundle = conversion(2, 5, Rounding::Down);
+        assert_eq!(bundle.apply(4), 10);
+        assert_eq!(bundle.apply(5), 12);
+        assert_eq!(bundle.needed_for(10), 4);
+        assert_eq!(bundle.needed_for(11), 5);
+    }
+
+    // `needed_for` is the least amount whose conversion covers what's wanted
+    #[test]
+    fn needed_for_is_the_least_that_covers() {
+        for rounding in [Rounding::Down, Rounding::Nearest, Rounding::Up] {
+            for (amount, counts_as) in [(45_000, 1), (10, 3), (3, 10), (1, 1), (7, 2)] {
+                let rule = conversion(amount, counts_as, rounding);
+                assert_eq!(rule.needed_for(0), 0);
+                for wanted in 1..50 {
+                    let needed = rule.needed_for(wanted);
+                    assert!(rule.apply(needed) >= wanted, "{:?} {}", rounding, wanted);
+                    assert!(rule.apply(needed - 1) < wanted, "{:?} {}", rounding, wanted);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn huge_amounts_saturate() {
+        let rule = conversion(1, 1_000, Rounding::Down);
+        assert_eq!(rule.apply(u64::MAX), u64::MAX);
+    }
+}